@@ -0,0 +1,52 @@
+use std::env;
+use std::fs;
+use std::time::Instant;
+use gameboy_core::game_boy::GameBoy;
+
+const FRAMES: u32 = 600;
+
+// Kjør med `cargo bench -p gameboy-core`. Sett GAMEBOY_BENCH_ROM for å måle på et ekte spill.
+fn main() {
+    let (name, rom) = match env::var("GAMEBOY_BENCH_ROM") {
+        Ok(path) => (path.clone(), fs::read(&path).expect("Kunne ikke lese ROM")),
+        Err(_) => ("syntetisk ROM".to_string(), synthetic_rom()),
+    };
+    let mut game_boy = GameBoy::new(rom, None).expect("Kunne ikke starte emulatoren");
+
+    // Varm opp forbi boot-ROM-en før vi begynner å måle
+    run_frames(&mut game_boy, 120);
+
+    let start = Instant::now();
+    run_frames(&mut game_boy, FRAMES);
+    let elapsed = start.elapsed();
+
+    println!("{}: {} frames på {:.2?} ({:.1} frames/s)", name, FRAMES, elapsed, FRAMES as f64 / elapsed.as_secs_f64());
+}
+
+fn run_frames(game_boy: &mut GameBoy, frames: u32) {
+    for _ in 0..frames {
+        game_boy.run_frame();
+        game_boy.updated_frame_buffer();
+    }
+}
+
+/// ROM uten MBC som skrur på skjermen med bakgrunn og sprites, fyller OAM og går i evig løkke
+fn synthetic_rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    let program: &[u8] = &[
+        0xf3,             // DI
+        0xaf,             // XOR A
+        0xe0, 0x40,       // LDH (0x40), A (skrur av skjermen så OAM kan skrives)
+        0x21, 0x00, 0xfe, // LD HL, 0xfe00
+        0x06, 0xa0,       // LD B, 0xa0
+        0x3e, 0x40,       // LD A, 0x40
+        0x22,             // loop: LD (HL+), A
+        0x05,             // DEC B
+        0x20, 0xfc,       // JR NZ, loop
+        0x3e, 0x93,       // LD A, 0x93 (LCD, BG, OBJ og tile data 0x8000)
+        0xe0, 0x40,       // LDH (0x40), A
+        0x18, 0xfe,       // JR -2
+    ];
+    rom[0x100..0x100 + program.len()].copy_from_slice(program);
+    rom
+}
@@ -2,44 +2,68 @@ use log::debug;
 use crate::apu::APU;
 use crate::bootrom::Bootrom;
 use crate::cartridge::Cartridge;
+use crate::hdma::{Hdma, HdmaMode};
 use crate::joypad::Joypad;
+use crate::model::{BootMode, Model};
 use crate::ppu::PPU;
+use crate::serial::{Serial, SerialDevice};
 use crate::timer::Timer;
 
 const WORK_RAM_SIZE: usize = 0x8000;
 const HIGH_RAM_SIZE: usize = 0x7f;
 
 pub struct AddressBus {
+    pub model: Model,
+    pub cgb_mode: bool,
     pub cartridge: Cartridge,
     pub ppu: PPU,
     apu: APU,
+    pub serial: Serial,
     work_ram: [u8; WORK_RAM_SIZE],
+    work_ram_bank: usize,
     high_ram: [u8; HIGH_RAM_SIZE],
     pub interrupt_enable_register: u8,
     pub interrupt_flag: u8,
     pub joypad: Joypad,
     bootrom: Bootrom,
     timer: Timer,
+    hdma: Hdma,
+    double_speed: bool,
+    speed_switch_armed: bool,
+    pub entered_vertical_blank: bool,
 }
 
 impl AddressBus {
-    pub fn new(cart: Cartridge) -> Self {
+    pub fn new(cart: Cartridge, model: Model, boot_mode: &BootMode, serial_device: Option<Box<dyn SerialDevice>>) -> Result<Self, &'static str> {
+        // DMG-spill på GBC kjører uten GBC-funksjonene
+        let cgb_mode = model == Model::Cgb && cart.cgb_mode();
         let mut address_bus = Self {
+            model,
+            cgb_mode,
             cartridge: cart,
-            ppu: PPU::new(),
+            ppu: PPU::new(cgb_mode),
             apu: APU::new(),
+            serial: Serial::new(serial_device),
             work_ram: [0; WORK_RAM_SIZE],
+            work_ram_bank: 1,
             high_ram: [0; HIGH_RAM_SIZE],
             interrupt_enable_register: 0,
             interrupt_flag: 0,
-            joypad: Joypad::new(),
-            bootrom: Bootrom::new(),
+            joypad: Joypad::new(model == Model::Sgb),
+            bootrom: Bootrom::new(boot_mode, model)?,
             timer: Timer::new(),
+            hdma: Hdma::new(),
+            double_speed: false,
+            speed_switch_armed: false,
+            entered_vertical_blank: false,
         };
-        #[cfg(feature = "test")] {
+        if !address_bus.bootrom.is_active() {
             address_bus.set_initial();
         }
-        address_bus
+        Ok(address_bus)
+    }
+    pub fn boot_rom_active(&self) -> bool {
+        self.bootrom.is_active()
     }
     pub fn cycle(&mut self, m_cycles: u32) -> u32 {
         self.timer.cycle(m_cycles);
@@ -48,22 +72,41 @@ impl AddressBus {
         
         self.interrupt_flag |= self.joypad.interrupt;
         self.joypad.interrupt = 0;
+
+        self.serial.cycle(4 * m_cycles);
+        self.interrupt_flag |= self.serial.interrupt;
+        self.serial.interrupt = 0;
         
-        let t_cycles = 4 * m_cycles;
+        // I dobbel hastighet går CPU og timer dobbelt så fort som PPU-en
+        let t_cycles = if self.double_speed { 2 * m_cycles } else { 4 * m_cycles };
         self.ppu.cycle(t_cycles);
+        if self.ppu.interrupt & 0x01 != 0 {
+            self.entered_vertical_blank = true;
+            self.apply_gameshark_codes();
+            if let Some(ref mut sgb) = self.joypad.sgb {
+                sgb.vertical_blank(self.ppu.read_frame_buffer());
+            }
+        }
         self.interrupt_flag |= self.ppu.interrupt;
         self.ppu.interrupt = 0;
+
+        if self.ppu.entered_horizontal_blank {
+            self.ppu.entered_horizontal_blank = false;
+            if self.hdma.mode == HdmaMode::HorizontalBlank {
+                self.copy_hdma_block();
+            }
+        }
         t_cycles
     }
     pub fn read_byte(&self, address: u16) -> u8 {
         match address {
             //0x00ff => panic!("Siste instruksjon i bootrom"),
-            0x0000 ..= 0x00ff if self.bootrom.is_active() => self.bootrom[address],
-            0x0000 ..= 0x7fff => self.cartridge.mbc.read_rom(address),
+            0x0000 ..= 0x08ff if self.bootrom.is_mapped(address) => self.bootrom[address],
+            0x0000 ..= 0x7fff => self.cartridge.read_rom(address),
             0x8000 ..= 0x9fff => self.ppu.read_video_ram(address),
             0xa000 ..= 0xbfff => self.cartridge.mbc.read_ram(address),
             0xc000 ..= 0xcfff | 0xe000 ..= 0xefff => self.work_ram[address as usize & 0x1fff],
-            0xd000 ..= 0xdfff | 0xf000 ..= 0xfdff => self.work_ram[address as usize & 0x1fff],
+            0xd000 ..= 0xdfff | 0xf000 ..= 0xfdff => self.work_ram[self.work_ram_bank * 0x1000 + (address as usize & 0x0fff)],
             0xfe00 ..= 0xfe9f => self.ppu.read_oam(address),
             0xfea0 ..= 0xfeff => panic!("Not usable!"),
             0xff00 ..= 0xff7f => self.io_read_byte((address & 0x00ff) as u8),
@@ -73,29 +116,41 @@ impl AddressBus {
             _ => { debug!("Minneadresse {:#04x} kan ikke leses fra. Returnerer 0xff.", address); 0xff }
         }
     }
+    // Som read_byte, men SRAM leses selv om den ikke er slått på, og adresser som ikke kan leses gir 0xff.
+    // Brukes med adresser fra brukeren, så den må aldri få panikk
+    pub fn peek_byte(&self, address: u16) -> u8 {
+        match address {
+            0xa000 ..= 0xbfff => self.cartridge.mbc.peek_ram(address),
+            0xfea0 ..= 0xfeff | 0xff50 => 0xff,
+            _ => self.read_byte(address),
+        }
+    }
     pub fn io_read_byte(&self, address: u8) -> u8 {
         match address {
             0x00 => self.joypad.read_byte(),
-            0x01 ..= 0x02 => panic!("Serial transfer not implemented"),
+            0x01 ..= 0x02 => self.serial.read_byte(address),
             0x04 ..= 0x07 => self.timer.read_byte(address),
             0x0f => self.interrupt_flag,
             0x10 ..= 0x26 => self.apu.read_byte(address),
             0x30 ..= 0x3f => self.apu.read_wave_byte(address),
             0x40 ..= 0x4b => self.ppu.read_byte(address),
-            0x4f => panic!("VRAM Bank Select is CGB feature"),
+            0x4d if self.cgb_mode => 0x7e | if self.double_speed { 0x80 } else { 0 } | self.speed_switch_armed as u8,
+            0x4f | 0x68 ..= 0x6c if self.cgb_mode => self.ppu.read_byte(address),
             0x50 => panic!("write-only"),
-            0x51 ..= 0x70 => panic!("Game Boy Color feature"),
+            0x51 ..= 0x55 if self.cgb_mode => self.hdma.read_byte(address),
+            0x70 if self.cgb_mode => 0xf8 | self.work_ram_bank as u8,
+            0x4d | 0x4f | 0x51 ..= 0x70 => { debug!("Game Boy Color-feature ved IO-minneadresse 0xff{:02x}", address); 0xff }
             _ => { debug!("IO-minneadresse {:#04x} kan ikke leses fra. Returnerer 0xff.", address); 0xff }
         }
     }
     pub fn write_byte(&mut self, address: u16, byte: u8) {
         match address {
-            0x0000 ..= 0x00ff if self.bootrom.is_active() => (),
+            0x0000 ..= 0x08ff if self.bootrom.is_mapped(address) => (),
             0x0000 ..= 0x7fff => self.cartridge.mbc.write_rom(address, byte),
             0x8000 ..= 0x9fff => self.ppu.write_video_ram(address, byte),
             0xa000 ..= 0xbfff => self.cartridge.mbc.write_ram(address, byte),
             0xc000 ..= 0xcfff | 0xe000 ..= 0xefff => self.work_ram[address as usize & 0x1fff] = byte,
-            0xd000 ..= 0xdfff | 0xf000 ..= 0xfdff => self.work_ram[address as usize & 0x1fff] = byte,
+            0xd000 ..= 0xdfff | 0xf000 ..= 0xfdff => self.work_ram[self.work_ram_bank * 0x1000 + (address as usize & 0x0fff)] = byte,
             0xfe00 ..= 0xfe9f => self.ppu.write_oam(address, byte),
             0xfea0 ..= 0xfeff => debug!("Not usable!"),
             0xff00 ..= 0xff7f => self.io_write_byte((address & 0x00ff) as u8, byte),
@@ -107,31 +162,58 @@ impl AddressBus {
     pub fn io_write_byte(&mut self, address: u8, byte: u8) {
         match address {
             0x00 => self.joypad.write_byte(byte),
-            0x01 ..= 0x02 => self.write_serial(byte),
+            0x01 ..= 0x02 => self.serial.write_byte(address, byte),
             0x04 ..= 0x07 => self.timer.write_byte(address, byte),
             0x0f => self.interrupt_flag = byte,
             0x10 ..= 0x26 => self.apu.write_byte(address, byte),
             0x30 ..= 0x3f => self.apu.write_wave_byte(address, byte),
             0x40 ..= 0x45 | 0x47 ..= 0x4b => self.ppu.write_byte(address, byte),
             0x46 => self.oam_dma(byte),
-            0x4f => debug!("VRAM Bank Select is CGB feature"),
+            0x4d if self.cgb_mode => self.speed_switch_armed = byte & 0x01 != 0,
+            0x4f | 0x68 ..= 0x6c if self.cgb_mode => self.ppu.write_byte(address, byte),
             0x50 => self.bootrom.deactivate(),
-            0x51 ..= 0x70 => debug!("Game Boy Color feature ved IO-minneadresse 0xff{:02x}", address),
+            0x51 ..= 0x55 if self.cgb_mode => self.write_hdma(address, byte),
+            0x70 if self.cgb_mode => self.work_ram_bank = match byte & 0x07 { 0 => 1, bank => bank as usize },
+            0x4d | 0x4f | 0x51 ..= 0x70 => debug!("Game Boy Color feature ved IO-minneadresse 0xff{:02x}", address),
             _ => debug!("IO-minneadresse 0xff{:02x} kan ikke skrives til.", address)
         }
     }
-    pub fn read_word(&self, address: u16) -> u16 {
-        (self.read_byte(address) as u16) | ((self.read_byte(address + 1) as u16) << 8)
+    // Skriver verdiene til GameShark-kodene. Kalles ved hver VBlank
+    fn apply_gameshark_codes(&mut self) {
+        // Lånes ut mens det skrives, så listen ikke må kopieres
+        let cheats = std::mem::take(&mut self.cartridge.cheats);
+        for (bank, address, value) in cheats.ram_writes() {
+            match (bank, address) {
+                (Some(bank), 0xa000 ..= 0xbfff) => self.cartridge.mbc.poke_ram(bank, address, value),
+                (Some(bank), 0xd000 ..= 0xdfff) if self.cgb_mode => {
+                    let bank = (bank as usize).max(1);
+                    self.work_ram[bank * 0x1000 + (address as usize & 0x0fff)] = value;
+                }
+                _ => self.write_byte(address, value),
+            }
+        }
+        self.cartridge.cheats = cheats;
+    }
+    // Kalles av STOP. Gir true hvis STOP ble brukt til å bytte hastighet (KEY1)
+    pub fn switch_speed(&mut self) -> bool {
+        if !self.speed_switch_armed { return false }
+
+        self.double_speed = !self.double_speed;
+        self.speed_switch_armed = false;
+        true
     }
-    pub fn write_word(&mut self, address: u16, word: u16) {
-        self.write_byte(address, (word & 0xff) as u8);
-        self.write_byte(address + 1, (word >> 8) as u8);
+    fn write_hdma(&mut self, address: u8, value: u8) {
+        self.hdma.write_byte(address, value);
+        while self.hdma.mode == HdmaMode::GeneralPurpose {
+            self.copy_hdma_block();
+        }
     }
-    fn write_serial(&self, byte: u8) {
-        if cfg!(feature = "test") {
-            if let Ok(s) = String::from_utf8(vec![byte]) { print!("{}", s); }
-        } else {
-            debug!("Serial transfer not implemented");
+    fn copy_hdma_block(&mut self) {
+        if let Some((source, destination)) = self.hdma.next_block() {
+            for i in 0..0x10 {
+                let byte = self.read_byte(source.wrapping_add(i));
+                self.ppu.write_video_ram(destination + i, byte);
+            }
         }
     }
     pub fn oam_dma(&mut self, value: u8) {
@@ -141,7 +223,6 @@ impl AddressBus {
             self.ppu.dma_write_oam(i, sprite);
         }
     }
-    #[cfg(feature = "test")]
     fn set_initial(&mut self) {
         self.write_byte(0xFF05, 0);
         self.write_byte(0xFF06, 0);
@@ -164,7 +245,8 @@ impl AddressBus {
         self.write_byte(0xFF23, 0xBF);
         self.write_byte(0xFF24, 0x77);
         self.write_byte(0xFF25, 0xF3);
-        self.write_byte(0xFF26, 0xF1);
+        // SGB-en har lyden av etter oppstart
+        self.write_byte(0xFF26, if self.model == Model::Sgb { 0xF0 } else { 0xF1 });
         self.write_byte(0xFF40, 0x91);
         self.write_byte(0xFF42, 0);
         self.write_byte(0xFF43, 0);
@@ -174,5 +256,8 @@ impl AddressBus {
         self.write_byte(0xFF49, 0xFF);
         self.write_byte(0xFF4A, 0);
         self.write_byte(0xFF4B, 0);
+        self.timer.set_divider(if self.model == Model::Dmg0 { 0x18 } else { 0xAB });
+        // IF er 0xE1 etter oppstart, men de øverste bitene lagres ikke her
+        self.interrupt_flag = 0x01;
     }
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+use std::io::{Cursor, Read};
+use flate2::read::GzDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use zip::ZipArchive;
+
+// Navnet som brukes når gzip-filen ikke har lagret filnavnet
+const GZIP_NAME: &str = "rom.gb";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Gzip,
+}
+
+impl ArchiveFormat {
+    // Formatet leses fra starten av filen, ikke filendelsen. Gir None for vanlige ROM-er
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b'P', b'K', 0x03, 0x04, ..] => Some(ArchiveFormat::Zip),
+            [0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c, ..] => Some(ArchiveFormat::SevenZip),
+            [0x1f, 0x8b, ..] => Some(ArchiveFormat::Gzip),
+            _ => None,
+        }
+    }
+}
+
+fn is_rom(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".gb") || name.ends_with(".gbc")
+}
+
+// Navnene på ROM-ene (.gb og .gbc) i arkivet, i rekkefølgen de er lagret. En gzip-fil har alltid én
+pub fn rom_names(bytes: &[u8]) -> Result<Vec<String>, &'static str> {
+    let names = match ArchiveFormat::detect(bytes).ok_or("Filen er ikke et zip-, 7z- eller gzip-arkiv")? {
+        ArchiveFormat::Zip => {
+            let archive = ZipArchive::new(Cursor::new(bytes)).map_err(|_| "Kunne ikke lese zip-arkivet")?;
+            archive.file_names().filter(|name| is_rom(name)).map(str::to_string).collect()
+        }
+        ArchiveFormat::SevenZip => {
+            let mut names = vec![];
+            seven_zip(bytes)?.for_each_entries(|entry, _| {
+                if !entry.is_directory() && is_rom(entry.name()) {
+                    names.push(entry.name().to_string());
+                }
+                Ok(true)
+            }).map_err(|_| "Kunne ikke lese 7z-arkivet")?;
+            names
+        }
+        ArchiveFormat::Gzip => {
+            let decoder = GzDecoder::new(bytes);
+            let name = decoder.header()
+                .and_then(|header| header.filename())
+                .map(|name| String::from_utf8_lossy(name).into_owned());
+            vec![name.unwrap_or_else(|| GZIP_NAME.to_string())]
+        }
+    };
+    if names.is_empty() { return Err("Fant ingen .gb- eller .gbc-fil i arkivet") }
+    Ok(names)
+}
+
+// Pakker ut ROM-en med navnet fra rom_names
+pub fn extract(bytes: &[u8], name: &str) -> Result<Vec<u8>, &'static str> {
+    let mut rom = vec![];
+    match ArchiveFormat::detect(bytes).ok_or("Filen er ikke et zip-, 7z- eller gzip-arkiv")? {
+        ArchiveFormat::Zip => {
+            let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|_| "Kunne ikke lese zip-arkivet")?;
+            let mut file = archive.by_name(name).map_err(|_| "Fant ikke filen i zip-arkivet")?;
+            file.read_to_end(&mut rom).map_err(|_| "Kunne ikke pakke ut filen fra zip-arkivet")?;
+        }
+        ArchiveFormat::SevenZip => {
+            let mut found = false;
+            // 7z-arkiver kan være solide, så filene må leses i rekkefølge frem til den riktige
+            seven_zip(bytes)?.for_each_entries(|entry, reader| {
+                if entry.is_directory() || entry.name() != name { return Ok(true) }
+                found = true;
+                reader.read_to_end(&mut rom)?;
+                Ok(false)
+            }).map_err(|_| "Kunne ikke pakke ut filen fra 7z-arkivet")?;
+            if !found { return Err("Fant ikke filen i 7z-arkivet") }
+        }
+        ArchiveFormat::Gzip => {
+            GzDecoder::new(bytes).read_to_end(&mut rom).map_err(|_| "Kunne ikke pakke ut gzip-filen")?;
+        }
+    }
+    Ok(rom)
+}
+
+fn seven_zip(bytes: &[u8]) -> Result<SevenZReader<Cursor<&[u8]>>, &'static str> {
+    SevenZReader::new(Cursor::new(bytes), bytes.len() as u64, Password::empty()).map_err(|_| "Kunne ikke lese 7z-arkivet")
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use crate::joypad::JoypadKey;
+
+// Tastene som skal være nede i hver frame, med én bit per tast som i JoypadKey::mask.
+// Skrives som `Down*2, Down+Right*2, Right+A, -*4`, der `-` er en frame uten taster
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputMacro {
+    frames: Vec<u8>,
+}
+
+impl InputMacro {
+    pub fn new(frames: Vec<u8>) -> Self {
+        Self { frames }
+    }
+    pub fn frames(&self) -> &[u8] {
+        &self.frames
+    }
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut frames = vec![];
+        for step in text.split(',') {
+            let (keys, count) = match step.split_once('*') {
+                Some((keys, count)) => (keys.trim(), count.trim().parse().map_err(|_| "Ugyldig antall frames i makroen")?),
+                None => (step.trim(), 1),
+            };
+            let mask = match keys {
+                "-" => 0,
+                _ => keys.split('+').try_fold(0, |mask, name| {
+                    JoypadKey::from_name(name.trim()).map(|key| mask | key.mask()).ok_or("Ukjent tast i makroen")
+                })?,
+            };
+            frames.extend(std::iter::repeat_n(mask, count));
+        }
+        if frames.is_empty() { return Err("Makroen er tom") }
+        Ok(Self { frames })
+    }
+}
+
+impl fmt::Display for InputMacro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, run) in self.frames.chunk_by(|a, b| a == b).enumerate() {
+            if i > 0 { write!(f, ", ")? }
+            let keys: Vec<_> = JoypadKey::ALL.iter().filter(|key| run[0] & key.mask() != 0).map(JoypadKey::name).collect();
+            match keys.is_empty() {
+                true => write!(f, "-")?,
+                false => write!(f, "{}", keys.join("+"))?,
+            }
+            if run.len() > 1 { write!(f, "*{}", run.len())? }
+        }
+        Ok(())
+    }
+}
+
+// Turboknapper og makroer. Regnes ut ved hver VBlank, så de gir samme kjøring hver gang og havner i filmer
+pub(crate) struct Autofire {
+    // Frames per trykk. Tasten er nede den første halvdelen
+    period: u32,
+    turbo: u8,
+    turbo_frames: [u32; 8],
+    running: Option<(InputMacro, usize)>,
+    recorded: Option<Vec<u8>>,
+}
+
+impl Autofire {
+    pub fn new() -> Self {
+        Self { period: 4, turbo: 0, turbo_frames: [0; 8], running: None, recorded: None }
+    }
+    pub fn set_period(&mut self, frames: u32) {
+        self.period = frames.max(2);
+    }
+    pub fn turbo_down(&mut self, key: JoypadKey) {
+        if self.turbo & key.mask() != 0 { return }
+        self.turbo |= key.mask();
+        self.turbo_frames[key.mask().trailing_zeros() as usize] = 0;
+    }
+    pub fn turbo_up(&mut self, key: JoypadKey) {
+        self.turbo &= !key.mask();
+    }
+    pub fn play(&mut self, input_macro: InputMacro) {
+        self.running = (!input_macro.frames.is_empty()).then_some((input_macro, 0));
+    }
+    pub fn start_recording(&mut self) {
+        self.recorded = Some(vec![]);
+    }
+    // Frames uten taster i starten og slutten tas ikke med
+    pub fn stop_recording(&mut self) -> Option<InputMacro> {
+        let frames = self.recorded.take()?;
+        let start = frames.iter().position(|&mask| mask != 0)?;
+        let end = frames.iter().rposition(|&mask| mask != 0)? + 1;
+        Some(InputMacro::new(frames[start..end].to_vec()))
+    }
+    pub fn recording(&self) -> bool {
+        self.recorded.is_some()
+    }
+    // Kalles ved VBlank med tastene som var nede i framen som ble ferdig. Gir tastene for neste frame
+    pub fn next_frame(&mut self, pressed: u8) -> u8 {
+        if let Some(ref mut frames) = self.recorded {
+            frames.push(pressed);
+        }
+        let mut mask = 0;
+        for (bit, frames) in self.turbo_frames.iter_mut().enumerate() {
+            if self.turbo & (1 << bit) == 0 { continue }
+            if *frames % self.period < self.period / 2 {
+                mask |= 1 << bit;
+            }
+            *frames += 1;
+        }
+        if let Some((ref input_macro, ref mut next)) = self.running {
+            mask |= input_macro.frames[*next];
+            *next += 1;
+            if *next >= input_macro.frames.len() {
+                self.running = None;
+            }
+        }
+        mask
+    }
+}
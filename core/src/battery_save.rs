@@ -1,4 +1,45 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 pub trait BatterySave {
-    fn load(&self, ram: &mut [u8]);
+    // Lagringen kan være kortere enn ram, f.eks. uten RTC-en. Gir antall byte som ble lest
+    fn load(&self, ram: &mut [u8]) -> usize;
     fn save(&self, data: &[u8]);
 }
+
+// Batterilagring i minnet. Lagringer forkastes, så filen på disk blir ikke endret
+pub struct MemoryBatterySave {
+    data: Vec<u8>,
+}
+
+impl MemoryBatterySave {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+}
+
+impl BatterySave for MemoryBatterySave {
+    fn load(&self, ram: &mut [u8]) -> usize {
+        let length = self.data.len().min(ram.len());
+        ram[..length].copy_from_slice(&self.data[..length]);
+        length
+    }
+    fn save(&self, _data: &[u8]) {}
+}
+
+// Husker det som ble lastet, slik at det kan lagres i en film
+pub(crate) struct CapturingBatterySave {
+    pub inner: Box<dyn BatterySave>,
+    pub loaded: Rc<RefCell<Vec<u8>>>,
+}
+
+impl BatterySave for CapturingBatterySave {
+    fn load(&self, ram: &mut [u8]) -> usize {
+        let length = self.inner.load(ram);
+        *self.loaded.borrow_mut() = ram[..length].to_vec();
+        length
+    }
+    fn save(&self, data: &[u8]) {
+        self.inner.save(data)
+    }
+}
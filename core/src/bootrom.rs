@@ -1,4 +1,5 @@
 use std::ops::Index;
+use crate::model::{BootMode, Model};
 
 /// Open Source DMG BootRom from "SameBoy":
 static DMG_BOOT: [u8; 256] = [
@@ -15,23 +16,28 @@ static DMG_BOOT: [u8; 256] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 224, 80
 ];
 
-const BOOTROM_SIZE: usize = 0x100;
-
 pub struct Bootrom {
-    data: [u8; BOOTROM_SIZE],
+    data: Vec<u8>,
     active: bool,
 }
 
 impl Bootrom {
-    pub fn new() -> Self {
-        Self {
-            data: DMG_BOOT,
-            active: if cfg!(feature = "test") { false } else { true },
-        }
+    pub fn new(boot_mode: &BootMode, model: Model) -> Result<Self, &'static str> {
+        let (data, active) = match boot_mode {
+            BootMode::External(data) if data.len() != model.boot_rom_size() => return Err("Boot-ROM-en har feil størrelse for denne modellen"),
+            BootMode::External(data) => (data.clone(), true),
+            BootMode::Embedded if model == Model::Dmg => (DMG_BOOT.to_vec(), true),
+            _ => (vec![], false),
+        };
+        Ok(Self { data, active })
     }
     pub fn is_active(&self) -> bool {
         self.active
     }
+    // GBC-boot-ROM-en er delt rundt cartridge-headeren i 0x0100-0x01ff
+    pub fn is_mapped(&self, address: u16) -> bool {
+        self.active && (address < 0x100 || (0x200..self.data.len() as u16).contains(&address))
+    }
     pub fn deactivate(&mut self) {
         self.active = false;
     }
@@ -42,4 +48,17 @@ impl Index<u16> for Bootrom {
     fn index(&self, index: u16) -> &u8 {
         &self.data[index as usize]
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_only_for_dmg() {
+        for model in [Model::Dmg0, Model::Dmg, Model::Mgb, Model::Sgb, Model::Cgb] {
+            let bootrom = Bootrom::new(&BootMode::Embedded, model).unwrap();
+            assert_eq!(bootrom.is_active(), model == Model::Dmg, "{}", model.name());
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::address_bus::AddressBus;
+
+// Det CPU-en ser av resten av maskinen. Gjør at CPU-en kan kjøres mot annet enn en hel Game Boy
+pub trait Bus {
+    fn read_byte(&self, address: u16) -> u8;
+    fn write_byte(&mut self, address: u16, value: u8);
+    fn read_word(&self, address: u16) -> u16 {
+        (self.read_byte(address) as u16) | ((self.read_byte(address.wrapping_add(1)) as u16) << 8)
+    }
+    fn write_word(&mut self, address: u16, word: u16) {
+        self.write_byte(address, (word & 0xff) as u8);
+        self.write_byte(address.wrapping_add(1), (word >> 8) as u8);
+    }
+    fn interrupt_enable(&self) -> u8;
+    fn interrupt_flag(&self) -> u8;
+    fn set_interrupt_flag(&mut self, value: u8);
+    // Lar resten av maskinen gå like mange M-sykluser som instruksjonen tok. Gir antall dots
+    fn tick(&mut self, m_cycles: u32) -> u32;
+    // Kalles av STOP. Gir true hvis STOP ble brukt til å bytte hastighet
+    fn switch_speed(&mut self) -> bool {
+        false
+    }
+    // Kalles når STOP stanser CPU-en
+    fn stop(&mut self) {}
+}
+
+impl Bus for AddressBus {
+    fn read_byte(&self, address: u16) -> u8 {
+        AddressBus::read_byte(self, address)
+    }
+    fn write_byte(&mut self, address: u16, value: u8) {
+        AddressBus::write_byte(self, address, value)
+    }
+    fn interrupt_enable(&self) -> u8 {
+        self.interrupt_enable_register
+    }
+    fn interrupt_flag(&self) -> u8 {
+        self.interrupt_flag
+    }
+    fn set_interrupt_flag(&mut self, value: u8) {
+        self.interrupt_flag = value
+    }
+    fn tick(&mut self, m_cycles: u32) -> u32 {
+        self.cycle(m_cycles)
+    }
+    fn switch_speed(&mut self) -> bool {
+        AddressBus::switch_speed(self)
+    }
+    fn stop(&mut self) {
+        // DIV nullstilles når STOP kjøres
+        self.io_write_byte(0x04, 0)
+    }
+}
+
+// Hele adresserommet som vanlig RAM, uten cartridge, PPU eller IO. IE og IF er egne felt,
+// så verdier i 0xff0f og 0xffff gir ikke interrupts
+pub struct FlatRam {
+    memory: Box<[u8]>,
+    pub interrupt_enable: u8,
+    pub interrupt_flag: u8,
+    pub m_cycles: u64,
+}
+
+impl FlatRam {
+    pub fn new() -> Self {
+        Self {
+            memory: vec![0; 0x10000].into_boxed_slice(),
+            interrupt_enable: 0,
+            interrupt_flag: 0,
+            m_cycles: 0,
+        }
+    }
+}
+
+impl Default for FlatRam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for FlatRam {
+    fn read_byte(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value
+    }
+    fn interrupt_enable(&self) -> u8 {
+        self.interrupt_enable
+    }
+    fn interrupt_flag(&self) -> u8 {
+        self.interrupt_flag
+    }
+    fn set_interrupt_flag(&mut self, value: u8) {
+        self.interrupt_flag = value
+    }
+    fn tick(&mut self, m_cycles: u32) -> u32 {
+        self.m_cycles += m_cycles as u64;
+        4 * m_cycles
+    }
+}
@@ -1,37 +1,62 @@
 use crate::battery_save::BatterySave;
+use crate::cheats::Cheats;
+use crate::header::CartridgeHeader;
 use crate::mbc::MBC;
 use crate::mbc::mbc_0::MBC0;
 use crate::mbc::mbc_1::MBC1;
 use crate::mbc::mbc_3::MBC3;
+use crate::mbc::mbc_5::MBC5;
+use crate::rtc::Clock;
 
 pub struct Cartridge {
-    header: Vec<u8>,
+    header: CartridgeHeader,
     pub mbc: Box<dyn MBC>,
+    pub cheats: Cheats,
 }
 
 impl Cartridge {
-    pub fn from_bytes(data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>) -> Result<Self, &'static str> {
-        let mut header = vec![0; 0x14f - 0x100 + 1];
-        header.copy_from_slice(&data[0x0100..=0x014f]);
-        
+    pub fn from_bytes(data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>, clock: Box<dyn Clock>) -> Result<Self, &'static str> {
         Ok(Self {
-            header,
+            header: CartridgeHeader::new(&data)?,
             mbc: match (data[0x147], data[0x148], data[0x149]) {
-                _ if cfg!(feature = "test") => Box::new(MBC0::new(data)),
                 (0x00, ..) => Box::new(MBC0::new(data)),
-                (0x03, 0x04, 0x02) => Box::new(MBC1::new(data, battery_save)),
-                (0x13, 0x05, 0x03) => Box::new(MBC3::new(data, battery_save)),
+                (0x01 ..= 0x03, ..) => Box::new(MBC1::new(data, battery_save)),
+                (0x0f ..= 0x13, ..) => Box::new(MBC3::new(data, battery_save, clock)),
+                (0x19 ..= 0x1e, ..) => Box::new(MBC5::new(data, battery_save)),
                 (mbc, rom_size, ram_size) => {
                     panic!("Støtter ikke denne MBC-en:\nMBC: {:#04x}\nROM size: {:#04x}\nRAM size: {:#04x}", mbc, rom_size, ram_size)
                 },
-            }
+            },
+            cheats: Cheats::new(),
         })
     }
+    // Game Genie-koder endrer det CPU-en leser, men ikke selve ROM-en
+    pub fn read_rom(&self, address: u16) -> u8 {
+        self.cheats.patch_rom(address, self.mbc.read_rom(address))
+    }
+    pub fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
     pub fn title(&self) -> String {
-        const TITLE_START: usize = 0x0134 - 0x0100;
-        const TITLE_END: usize = 0x0143 - 0x0100;
-        
-        String::from_utf8(self.header[TITLE_START..=TITLE_END].to_owned()).unwrap()
+        self.header.title()
+    }
+    pub fn cgb_mode(&self) -> bool {
+        self.header.cgb_mode()
+    }
+    pub fn sgb_support(&self) -> bool {
+        self.header.sgb_support()
+    }
+    pub fn has_ram(&self) -> bool {
+        self.header.has_ram()
+    }
+    pub fn header_checksum(&self) -> u8 {
+        self.header.header_checksum()
+    }
+    pub fn title_checksum(&self) -> u8 {
+        self.header.title_checksum()
+    }
+    pub fn nintendo_licensee(&self) -> bool {
+        self.header.nintendo_licensee()
     }
     pub fn manual_save(&self) {
         self.mbc.manual_save()
@@ -0,0 +1,178 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheatKind {
+    // Skriver verdien til minnet ved hver VBlank. Banken gjelder SRAM, og WRAM 0xd000-0xdfff på GBC
+    GameShark { bank: Option<u8>, address: u16, value: u8 },
+    // Bytter ut en byte når ROM-en leses, eventuelt bare når den opprinnelige byten er lik compare
+    GameGenie { address: u16, value: u8, compare: Option<u8> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cheat {
+    pub code: String,
+    pub description: String,
+    pub enabled: bool,
+    pub kind: CheatKind,
+}
+
+impl Cheat {
+    // GameShark skrives `01VVAAAA` med adressen i little endian, Game Genie `VVA-AAA-CCC` eller `VVA-AAA`
+    pub fn parse(code: &str, description: &str) -> Result<Self, &'static str> {
+        let code = code.trim().to_ascii_uppercase();
+        let digits: Vec<u8> = code.chars().filter(|c| *c != '-')
+            .map(|c| c.to_digit(16).map(|digit| digit as u8).ok_or("Juksekoden kan bare ha heksadesimale sifre"))
+            .collect::<Result<_, _>>()?;
+        let byte = |i: usize| digits[i] << 4 | digits[i + 1];
+        let kind = match digits.len() {
+            8 => CheatKind::GameShark {
+                bank: match byte(0) {
+                    0x01 => None,
+                    0x80 ..= 0x87 | 0x90 ..= 0x97 => Some(byte(0) & 0x07),
+                    _ => return Err("Ukjent type GameShark-kode"),
+                },
+                address: (byte(6) as u16) << 8 | byte(4) as u16,
+                value: byte(2),
+            },
+            6 | 9 => CheatKind::GameGenie {
+                address: ((digits[5] ^ 0x0f) as u16) << 12 | (digits[2] as u16) << 8 | (digits[3] as u16) << 4 | digits[4] as u16,
+                value: byte(0),
+                // Det midterste sifferet i siste gruppe brukes ikke
+                compare: (digits.len() == 9).then(|| (digits[6] << 4 | digits[8]).rotate_right(2) ^ 0xba),
+            },
+            _ => return Err("Juksekoden må være en GameShark-kode (8 sifre) eller Game Genie-kode (6 eller 9 sifre)"),
+        };
+        match kind {
+            CheatKind::GameShark { address: 0xa000 ..= 0xdfff | 0xff80 ..= 0xfffe, .. } => {}
+            // Skrivinger til 0x0000-0x7fff ville endret bankene til MBC-en ved hver frame
+            CheatKind::GameShark { .. } => return Err("GameShark-koden må peke på SRAM, WRAM eller HRAM"),
+            CheatKind::GameGenie { address: 0x8000 .., .. } => return Err("Game Genie-koden peker utenfor ROM-en"),
+            CheatKind::GameGenie { .. } => {}
+        }
+        Ok(Self { code, description: description.trim().to_string(), enabled: true, kind })
+    }
+}
+
+// Juksekodene til spillet. Game Genie-kodene brukes av Cartridge når ROM-en leses,
+// og GameShark-kodene av AddressBus ved hver VBlank
+#[derive(Clone, Debug, Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Self { cheats: vec![] }
+    }
+    // Én kode per linje, med beskrivelsen etter koden. Linjer som starter med `-` er slått av, og `#` er kommentarer
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut cheats = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+            let (enabled, line) = match line.strip_prefix('-') {
+                Some(line) => (false, line.trim_start()),
+                None => (true, line.strip_prefix('+').unwrap_or(line).trim_start()),
+            };
+            let (code, description) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let mut cheat = Cheat::parse(code, description).map_err(|err| format!("Linje {}: {}", number + 1, err))?;
+            cheat.enabled = enabled;
+            cheats.add(cheat);
+        }
+        Ok(cheats)
+    }
+    pub fn to_text(&self) -> String {
+        self.cheats.iter()
+            .map(|cheat| format!("{}{} {}\n", if cheat.enabled { "" } else { "-" }, cheat.code, cheat.description))
+            .collect()
+    }
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat)
+    }
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = enabled
+        }
+    }
+    pub fn list(&self) -> &[Cheat] {
+        &self.cheats
+    }
+    pub(crate) fn patch_rom(&self, address: u16, value: u8) -> u8 {
+        self.cheats.iter().filter(|cheat| cheat.enabled).fold(value, |patched, cheat| match cheat.kind {
+            CheatKind::GameGenie { address: a, value: v, compare } if a == address && compare.is_none_or(|c| c == value) => v,
+            _ => patched,
+        })
+    }
+    pub(crate) fn ram_writes(&self) -> impl Iterator<Item = (Option<u8>, u16, u8)> + '_ {
+        self.cheats.iter().filter(|cheat| cheat.enabled).filter_map(|cheat| match cheat.kind {
+            CheatKind::GameShark { bank, address, value } => Some((bank, address, value)),
+            CheatKind::GameGenie { .. } => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(code: &str) -> CheatKind {
+        Cheat::parse(code, "").unwrap().kind
+    }
+
+    #[test]
+    fn game_shark() {
+        // Adressen er little endian: 01 VV AAAA med D016 lagret som 16 D0
+        assert_eq!(kind("01FF16D0"), CheatKind::GameShark { bank: None, address: 0xd016, value: 0xff });
+        assert_eq!(kind("010980FF"), CheatKind::GameShark { bank: None, address: 0xff80, value: 0x09 });
+        assert_eq!(kind("9163A0C0"), CheatKind::GameShark { bank: Some(1), address: 0xc0a0, value: 0x63 });
+        assert_eq!(kind("83001DA0"), CheatKind::GameShark { bank: Some(3), address: 0xa01d, value: 0x00 });
+    }
+
+    #[test]
+    fn game_genie() {
+        // Adressen er sifrene 6, 3, 4 og 5, der siffer 6 er XOR-et med F
+        assert_eq!(kind("00A-17B"), CheatKind::GameGenie { address: 0x4a17, value: 0x00, compare: None });
+        // Sammenligningsbyten er siffer 7 og 9, rotert 2 bit til høyre og XOR-et med BA
+        assert_eq!(kind("00A-17B-C49"), CheatKind::GameGenie { address: 0x4a17, value: 0x00, compare: Some(0xc8) });
+        assert_eq!(kind("3E0-D4F-E6E"), CheatKind::GameGenie { address: 0x00d4, value: 0x3e, compare: Some(0x01) });
+        assert_eq!(kind("fa0-6ae-2a2"), kind("FA0-6AE-2A2"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Cheat::parse("01FF16D", "").is_err());
+        assert!(Cheat::parse("01FF16DX", "").is_err());
+        assert!(Cheat::parse("00A-17B-C4", "").is_err());
+        // Ukjent type GameShark-kode
+        assert!(Cheat::parse("02FF16D0", "").is_err());
+        // GameShark-koder kan ikke skrive til ROM, VRAM eller IO
+        assert!(Cheat::parse("01FF0020", "").is_err());
+        assert!(Cheat::parse("01FF0080", "").is_err());
+        assert!(Cheat::parse("01FF40FF", "").is_err());
+        // Game Genie-adressen må være i ROM
+        assert!(Cheat::parse("00A-177", "").is_err());
+    }
+
+    #[test]
+    fn cheat_file() {
+        let text = "# Super Mario Land\n01FF16D0 Uendelig liv\n\n-00A-17B-C49  Starter på brett 3\n";
+        let cheats = Cheats::parse(text).unwrap();
+        assert_eq!(cheats.list().len(), 2);
+        assert_eq!(cheats.list()[0].description, "Uendelig liv");
+        assert!(!cheats.list()[1].enabled);
+        assert_eq!(cheats.to_text(), "01FF16D0 Uendelig liv\n-00A-17B-C49 Starter på brett 3\n");
+        assert_eq!(Cheats::parse("01FF16D0\n01FF0020 ROM").unwrap_err(), "Linje 2: GameShark-koden må peke på SRAM, WRAM eller HRAM");
+    }
+
+    #[test]
+    fn patches_rom_only_when_compare_matches() {
+        let mut cheats = Cheats::parse("00A-17B-C49\n3E0-D4F").unwrap();
+        assert_eq!(cheats.patch_rom(0x4a17, 0xc8), 0x00);
+        assert_eq!(cheats.patch_rom(0x4a17, 0xc9), 0xc9);
+        assert_eq!(cheats.patch_rom(0x00d4, 0x12), 0x3e);
+        cheats.set_enabled(1, false);
+        assert_eq!(cheats.patch_rom(0x00d4, 0x12), 0x12);
+        assert_eq!(cheats.ram_writes().count(), 0);
+    }
+}
@@ -7,31 +7,110 @@ mod read_write;
 mod condition;
 
 use std::fmt::Debug;
-use crate::cartridge::Cartridge;
 use crate::address_bus::AddressBus;
+use crate::bus::Bus;
 use registers::Registers;
-use crate::battery_save::BatterySave;
 use crate::cpu::interrupt_master_enable::InterruptMasterEnable;
 
-pub struct CPU {
+// Øyeblikksbilde av registrene, for tester og feilsøking
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CpuState {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub ime: bool,
+    pub halted: bool,
+}
+
+pub struct CPU<B: Bus = AddressBus> {
     registers: Registers,
-    pub bus: AddressBus,
+    pub bus: B,
     is_halted: bool,
+    // Etter STOP står CPU-en til en tast trykkes. Resten av maskinen går videre, så frames fortsetter å komme
+    is_stopped: bool,
     interrupt_master_enable: InterruptMasterEnable,
 }
 
 impl CPU {
-    pub fn new(cartridge_data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>) -> Result<Self, &'static str> {
-        let cartridge = Cartridge::from_bytes(cartridge_data, battery_save)?;
-        
-        Ok(Self {
+    pub fn new(bus: AddressBus) -> Self {
+        let mut cpu = Self::with_bus(bus);
+        if !cpu.bus.boot_rom_active() {
+            cpu.registers.set_state_after_boot_rom(cpu.bus.model, &cpu.bus.cartridge);
+        }
+        cpu
+    }
+}
+
+impl<B: Bus> CPU<B> {
+    // Alle registre er 0. Brukes for å kjøre CPU-en mot egen minnemodell
+    pub fn with_bus(bus: B) -> Self {
+        Self {
             registers: Registers::new(),
-            bus: AddressBus::new(cartridge),
+            bus,
             is_halted: false,
+            is_stopped: false,
             interrupt_master_enable: InterruptMasterEnable::new(),
-        })
+        }
+    }
+    pub fn program_counter(&self) -> u16 {
+        self.registers.pc
+    }
+    pub fn state(&self) -> CpuState {
+        let registers = &self.registers;
+        CpuState {
+            a: registers.a,
+            f: u8::from(registers.f),
+            b: registers.b,
+            c: registers.c,
+            d: registers.d,
+            e: registers.e,
+            h: registers.h,
+            l: registers.l,
+            sp: registers.sp,
+            pc: registers.pc,
+            ime: self.interrupt_master_enable.is_enabled(),
+            halted: self.is_halted,
+        }
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.is_stopped
+    }
+    pub fn wake_from_stop(&mut self) {
+        self.is_stopped = false
+    }
+    pub fn set_state(&mut self, state: CpuState) {
+        let registers = &mut self.registers;
+        registers.a = state.a;
+        registers.f = state.f.into();
+        registers.b = state.b;
+        registers.c = state.c;
+        registers.d = state.d;
+        registers.e = state.e;
+        registers.h = state.h;
+        registers.l = state.l;
+        registers.sp = state.sp;
+        registers.pc = state.pc;
+        self.interrupt_master_enable.set(state.ime);
+        self.is_halted = state.halted;
+        // CpuState har ikke STOP, så en ny tilstand starter alltid CPU-en igjen
+        self.is_stopped = false;
+    }
+    // Kjører én instruksjon og lar bussen gå like lenge. Gir antall dots
+    pub fn step(&mut self) -> u32 {
+        let m_cycles = self.cycle();
+        self.bus.tick(m_cycles)
     }
     pub fn cycle(&mut self) -> u32 {
+        if self.is_stopped {
+            return 1
+        }
         let interrupts_handle_time = self.handle_interrupts();
         if interrupts_handle_time > 0 {
             return interrupts_handle_time
@@ -47,7 +126,7 @@ impl CPU {
         if !interrupt_master_enable && !self.is_halted {
             return 0
         }
-        let interrupts = self.bus.interrupt_enable_register & self.bus.interrupt_flag;
+        let interrupts = self.bus.interrupt_enable() & self.bus.interrupt_flag();
         if interrupts == 0 {
             return 0
         }
@@ -59,7 +138,7 @@ impl CPU {
         if highest_priority_bit > 4 {
             panic!("Ugyldig interrupt-verdi")
         }
-        self.bus.interrupt_flag &= !(1 << highest_priority_bit);
+        self.bus.set_interrupt_flag(self.bus.interrupt_flag() & !(1 << highest_priority_bit));
         self.push_stack(self.registers.pc);
 
         self.registers.pc = match highest_priority_bit {
@@ -94,10 +173,26 @@ impl CPU {
     }
 }
 
-impl Debug for CPU {
+impl<B: Bus> Debug for CPU<B> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let pc_mem: Vec<u8> = Vec::from_iter((0..4).map(|pc_offset| self.bus.read_byte(self.registers.pc + pc_offset)));
+        let pc_mem: Vec<u8> = Vec::from_iter((0..4).map(|pc_offset| self.bus.read_byte(self.registers.pc.wrapping_add(pc_offset))));
         write!(f, "{:?} PCMEM:{:02x},{:02x},{:02x},{:02x}",
             self.registers, pc_mem[0], pc_mem[1], pc_mem[2], pc_mem[3])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::FlatRam;
+
+    #[test]
+    fn set_state_wakes_from_stop() {
+        let mut cpu = CPU::with_bus(FlatRam::new());
+        cpu.bus.write_byte(0x0000, 0x10);
+        cpu.cycle();
+        assert!(cpu.is_stopped());
+        cpu.set_state(CpuState::default());
+        assert!(!cpu.is_stopped());
+    }
+}
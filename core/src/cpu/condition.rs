@@ -1,3 +1,4 @@
+use crate::bus::Bus;
 use crate::cpu::CPU;
 
 pub enum Condition {
@@ -8,7 +9,7 @@ pub enum Condition {
     True,
 }
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     pub fn check_condition(&self, condition: Condition) -> bool {
         match condition {
             Condition::Zero => self.registers.f.zero,
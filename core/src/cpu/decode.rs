@@ -1,11 +1,12 @@
 use log::debug;
 use crate::cpu::condition::Condition::{Carry, NotCarry, NotZero, True, Zero};
+use crate::bus::Bus;
 use crate::cpu::CPU;
 use crate::cpu::read_write::Operand::{RegA, RegB, RegC, RegD, RegE, RegH, RegL, AddressBC, AddressDE, AddressHL, AddressHLI, AddressHLD, Immediate8};
 use crate::cpu::registers::Reg8::{A, B, C, D, E, H, L};
 use crate::cpu::registers::Reg16::{AF, BC, DE, HL, SP};
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     pub fn decode(&mut self) -> u32 {
         let opcode = self.fetch_byte();
         debug!("Dekoder nå opkode {:#04x}", opcode);
@@ -26,7 +27,7 @@ impl CPU {
             0x0d => { self.alu_dec(RegC); 1 }
             0x0e => { self.load(RegC, Immediate8); 2 }
             0x0f => { self.rrca(); 1 }
-            0x10 => { panic!("STOP") }
+            0x10 => { self.stop() }
             0x11 => { let word = self.fetch_word(); self.registers.write_16(DE, word); 3 }
             0x12 => { self.load(AddressDE, RegA); 2 }
             0x13 => { self.inc_16(DE); 2 }
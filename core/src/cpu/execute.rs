@@ -1,10 +1,11 @@
 use crate::cpu::condition::Condition;
+use crate::bus::Bus;
 use crate::cpu::CPU;
 use crate::cpu::read_write::Operand;
 use crate::cpu::read_write::Operand::RegA;
 use crate::cpu::registers::Reg16;
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     pub fn inc_16(&mut self, reg: Reg16) {
         let value = self.registers.read_16(reg).wrapping_add(1);
         self.registers.write_16(reg, value);
@@ -130,6 +131,16 @@ impl CPU {
         self.registers.f.half_carry = false;
         self.registers.f.carry = !self.registers.f.carry;
     }
+    pub fn stop(&mut self) -> u32 {
+        // STOP er to byte lang
+        self.fetch_byte();
+        if self.bus.switch_speed() {
+            return 1
+        }
+        self.is_stopped = true;
+        self.bus.stop();
+        1
+    }
     pub fn load(&mut self, destination: Operand, source: Operand) {
         let value = self.read(source);
         self.write(destination, value);
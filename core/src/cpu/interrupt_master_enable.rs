@@ -26,6 +26,14 @@ impl InterruptMasterEnable {
         };
         self.value
     }
+    pub fn is_enabled(&self) -> bool {
+        self.value
+    }
+    pub fn set(&mut self, value: bool) {
+        self.value = value;
+        self.enable_counter = 0;
+        self.disable_counter = 0;
+    }
     pub fn ei(&mut self) {
         self.enable_counter = 2;
     }
@@ -1,7 +1,8 @@
+use crate::bus::Bus;
 use crate::cpu::CPU;
 use crate::cpu::registers::{Reg16, Reg8};
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     pub fn read(&mut self, operand: Operand) -> u8 {
         match operand {
             Operand::RegA => self.registers.read_8(Reg8::A),
@@ -1,5 +1,7 @@
 use std::fmt::Debug;
+use crate::cartridge::Cartridge;
 use crate::cpu::flags_register::FlagsRegister;
+use crate::model::Model;
 
 pub struct Registers {
     pub a: u8,
@@ -37,7 +39,7 @@ pub enum Reg16 {
 
 impl Registers {
     pub fn new() -> Self {
-        let mut registers = Registers {
+        Registers {
             a: 0,
             b: 0,
             c: 0,
@@ -53,26 +55,30 @@ impl Registers {
             l: 0,
             pc: 0,
             sp: 0,
-        };
-        if cfg!(feature = "test") {
-            registers.set_state_after_boot_rom();
         }
-        registers
     }
-    pub fn set_state_after_boot_rom(&mut self) {
-        self.a = 0x01;
-        self.b = 0x00;
-        self.c = 0x13;
-        self.d = 0x00;
-        self.e = 0xd8;
-        self.f = FlagsRegister {
-                zero: true,
-                subtract: false,
-                half_carry: true,
-                carry: true,
-            };
-        self.h = 0x01;
-        self.l = 0x4d;
+    // Registrene slik boot-ROM-en til hver modell etterlater dem, se Pan Docs "Power Up Sequence"
+    pub fn set_state_after_boot_rom(&mut self, model: Model, cartridge: &Cartridge) {
+        // H og C settes av DMG-boot-ROM-en med mindre header-sjekksummen er 0
+        let checksum_flags = cartridge.header_checksum() != 0;
+        let (a, f, b, c, d, e, h, l) = match model {
+            Model::Dmg0 => (0x01, 0x00, 0xff, 0x13, 0x00, 0xc1, 0x84, 0x03),
+            Model::Dmg => (0x01, 0x80 | if checksum_flags { 0x30 } else { 0 }, 0x00, 0x13, 0x00, 0xd8, 0x01, 0x4d),
+            Model::Mgb => (0xff, 0x80 | if checksum_flags { 0x30 } else { 0 }, 0x00, 0x13, 0x00, 0xd8, 0x01, 0x4d),
+            Model::Sgb => (0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xc0, 0x60),
+            // A = 0x11 er hvordan spill oppdager at de kjører på en GBC
+            Model::Cgb if cartridge.cgb_mode() => (0x11, 0x80, 0x00, 0x00, 0xff, 0x56, 0x00, 0x0d),
+            // DMG-spill på GBC: B er summen av tittelen for Nintendo-spill, som boot-ROM-en bruker til å velge palett
+            Model::Cgb => (0x11, 0x80, if cartridge.nintendo_licensee() { cartridge.title_checksum() } else { 0x00 }, 0x00, 0x00, 0x08, 0x00, 0x7c),
+        };
+        self.a = a;
+        self.f = FlagsRegister::from(f);
+        self.b = b;
+        self.c = c;
+        self.d = d;
+        self.e = e;
+        self.h = h;
+        self.l = l;
         self.pc = 0x0100;
         self.sp = 0xfffe;
     }
@@ -1,27 +1,48 @@
+use crate::palette::Palette;
+
+// En DMG-piksel i framebufferen består av skyggen (bit 0-1), hvilket palett-register den kom fra (bit 2-3)
+// og om laget fremheves med LayerMask (bit 4). En GBC-piksel har bit 15 satt og fargen i RGB555 i bit 0-14.
+pub const OBJ_PALETTE_0: u16 = 1 << 2;
+pub const OBJ_PALETTE_1: u16 = 2 << 2;
+pub const HIGHLIGHTED: u16 = 1 << 4;
+pub const CGB_COLOR: u16 = 1 << 15;
+const PALETTE_SOURCE: u16 = OBJ_PALETTE_0 | OBJ_PALETTE_1;
+
 pub trait FrameBuffer {
-    fn write_to_rbga_buffer(&self, rgba_buffer: &mut [u8]);
+    fn write_to_rbga_buffer(&self, palette: &Palette, rgba_buffer: &mut [u8]);
 }
 
-impl FrameBuffer for Vec<u8> {
-    fn write_to_rbga_buffer(&self, rgba_buffer: &mut [u8]) {
-        for (i, byte) in self.iter().enumerate() {
+impl FrameBuffer for [u16] {
+    fn write_to_rbga_buffer(&self, palette: &Palette, rgba_buffer: &mut [u8]) {
+        for (i, pixel) in self.iter().enumerate() {
             let pixel_index = i * 4;
-            let color = if cfg!(feature = "green") {
-                match *byte {
-                    0 => [0x9b, 0xbc, 0x0f, 0xff], // Lysest grønn
-                    1 => [0x8b, 0xac, 0x0f, 0xff], // Lys grønn
-                    2 => [0x30, 0x62, 0x30, 0xff], // Mørk grønn
-                    _ => [0x0f, 0x38, 0x0f, 0xff], // Mørkest grønn
-                }
-            } else {
-                match *byte {
-                    0 => [0xff, 0xff, 0xff, 0xff], // Hvit
-                    1 => [0xaa, 0xaa, 0xaa, 0xff], // Lys grå
-                    2 => [0x55, 0x55, 0x55, 0xff], // Mørk grå
-                    _ => [0x00, 0x00, 0x00, 0xff], // Svart
-                }
-            } ;
-            rgba_buffer[pixel_index..pixel_index + 4].copy_from_slice(&color);
+            rgba_buffer[pixel_index..pixel_index + 4].copy_from_slice(&pixel_to_rgba(*pixel, palette));
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn pixel_to_rgba(pixel: u16, palette: &Palette) -> [u8; 4] {
+    if pixel & CGB_COLOR != 0 {
+        return rgb555_to_rgba(pixel)
+    }
+    let shades = match pixel & PALETTE_SOURCE {
+        OBJ_PALETTE_0 => &palette.obj_0,
+        OBJ_PALETTE_1 => &palette.obj_1,
+        _ => &palette.background,
+    };
+    match pixel & HIGHLIGHTED {
+        0 => shades.rgba(pixel as u8),
+        _ => highlight(shades.rgba(pixel as u8)),
+    }
+}
+
+pub fn rgb555_to_rgba(color: u16) -> [u8; 4] {
+    // Skalerer 5-bits kanaler til 8 bit ved å gjenta de øverste bitene
+    let scale = |channel: u16| ((channel << 3) | (channel >> 2)) as u8;
+    [scale(color & 0x1f), scale((color >> 5) & 0x1f), scale((color >> 10) & 0x1f), 0xff]
+}
+
+fn highlight([red, green, blue, alpha]: [u8; 4]) -> [u8; 4] {
+    // Blander fargen med magenta, slik at skyggene fortsatt kan skilles fra hverandre
+    [red / 2 + 0x80, green / 2, blue / 2 + 0x80, alpha]
+}
@@ -1,37 +1,285 @@
+use log::info;
+use crate::autofire::{Autofire, InputMacro};
 use crate::battery_save::BatterySave;
-use crate::cpu::CPU;
+use crate::cheats::Cheats;
+use crate::cpu::{CpuState, CPU};
 use crate::joypad::JoypadKey;
+use crate::frame_buffer::FrameBuffer;
+use crate::game_boy_builder::GameBoyBuilder;
+use crate::model::Model;
+use crate::movie::{Movie, MovieEvent, MoviePlayer};
+use crate::pacing::DOTS_PER_FRAME;
+use crate::palette::Palette;
+use crate::ppu::layer_mask::LayerMask;
+use crate::ppu::sprite::Sprite;
+use crate::ppu::viewer::TileMap;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH, SGB_SCREEN_HEIGHT, SGB_SCREEN_WIDTH};
 
 pub struct GameBoy {
     cpu: CPU,
+    palette: Palette,
+    // Antall VBlank siden start, og dots siden forrige. Brukes som tidsstempel i filmer
+    frame: u32,
+    frame_dots: u32,
+    recording: Option<Movie>,
+    playback: Option<MoviePlayer>,
+    // Tastene brukeren holder nede, tastene turbo og makroer trykker, og det joypaden faktisk ser
+    keys: u8,
+    injected: u8,
+    pressed: u8,
+    autofire: Autofire,
 }
 
 impl GameBoy {
     pub fn new(cartridge_data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>) -> Result<Box<Self>, &'static str> {
-        Ok(Box::new(Self {
-            cpu: CPU::new(cartridge_data, battery_save)?,
-        }))
+        let builder = GameBoyBuilder::new(cartridge_data);
+        match battery_save {
+            Some(battery_save) => builder.battery_save(battery_save).build(),
+            None => builder.build(),
+        }
+    }
+    pub fn builder(cartridge_data: Vec<u8>) -> GameBoyBuilder {
+        GameBoyBuilder::new(cartridge_data)
+    }
+    pub(crate) fn new_with_cpu(cpu: CPU, palette: Palette) -> Self {
+        Self { cpu, palette, frame: 0, frame_dots: 0, recording: None, playback: None,
+            keys: 0, injected: 0, pressed: 0, autofire: Autofire::new() }
+    }
+    pub fn model(&self) -> Model {
+        self.cpu.bus.model
+    }
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette
     }
     pub fn emulate(&mut self) -> u32 {
-        let m_cycles = self.cpu.cycle();
-        self.cpu.bus.cycle(m_cycles)
+        if let Some(ref mut playback) = self.playback {
+            while let Some(event) = playback.next_due(self.frame, self.frame_dots) {
+                Self::set_joypad_key(&mut self.cpu, event.key, event.pressed);
+                // Filmen har allerede med turbo og makroer, så etter den er ferdig gjelder tastene slik de står
+                self.pressed = (self.pressed & !event.key.mask()) | if event.pressed { event.key.mask() } else { 0 };
+                self.keys = self.pressed;
+                self.injected = 0;
+            }
+            if playback.finished() {
+                info!("Filmen er ferdig etter {} frames", self.frame);
+                self.playback = None;
+            }
+        }
+        self.cpu.bus.entered_vertical_blank = false;
+        let dots = self.cpu.step();
+        if self.cpu.bus.entered_vertical_blank {
+            self.frame += 1;
+            self.frame_dots = 0;
+            if self.playback.is_none() {
+                self.injected = self.autofire.next_frame(self.pressed);
+                self.update_keys();
+            }
+        } else {
+            self.frame_dots += dots;
+        }
+        dots
+    }
+    // Emulerer til neste VBlank, eller én frame med dots når skjermen er av. Gir antall dots som gikk
+    pub fn run_frame(&mut self) -> u32 {
+        self.run_frame_until(|_| false).0
+    }
+    // Som run_frame, men stopper etter første instruksjon der stop gir true. Gir dots og om den stoppet tidlig
+    pub fn run_frame_until(&mut self, mut stop: impl FnMut(&GameBoy) -> bool) -> (u32, bool) {
+        let mut dots = 0;
+        loop {
+            dots += self.emulate();
+            if stop(self) { return (dots, true) }
+            if self.cpu.bus.entered_vertical_blank { break }
+            if !self.cpu.bus.ppu.lcd_on() && dots >= DOTS_PER_FRAME { break }
+        }
+        (dots, false)
+    }
+    // Emulerer flere frames, men tegner bare den siste (frame skip)
+    pub fn run_frames(&mut self, frames: u32) -> u32 {
+        let mut dots = 0;
+        for frame in 0..frames {
+            // SGB-en leser VRAM-overføringer fra skjermbildet, så der tegnes alle frames
+            self.cpu.bus.ppu.skip_rendering = frame + 1 < frames && self.cpu.bus.joypad.sgb.is_none();
+            dots += self.run_frame();
+        }
+        self.cpu.bus.ppu.skip_rendering = false;
+        dots
+    }
+    // Emulerer minst så mange dots. Gir antall dots som faktisk gikk, siden en instruksjon ikke kan deles
+    pub fn run_cycles(&mut self, dots: u32) -> u32 {
+        let mut elapsed = 0;
+        while elapsed < dots {
+            elapsed += self.emulate();
+        }
+        elapsed
+    }
+    pub fn updated_frame_buffer(&mut self) -> Option<&[u16]> {
+        match (self.cpu.bus.ppu.check_and_reset_updated(), &self.cpu.bus.joypad.sgb) {
+            (true, Some(sgb)) => Some(sgb.read_frame_buffer()),
+            (true, None) => Some(self.cpu.bus.ppu.read_frame_buffer()),
+            (false, _) => None
+        }
+    }
+    // Skriver en ny frame som RGBA med valgt palett. Gir false hvis ingenting er endret
+    pub fn write_updated_frame(&mut self, rgba_buffer: &mut [u8]) -> bool {
+        if !self.cpu.bus.ppu.check_and_reset_updated() { return false }
+
+        self.write_frame(rgba_buffer);
+        true
+    }
+    // Skriver gjeldende frame som RGBA, uansett om den er endret
+    pub fn write_frame(&self, rgba_buffer: &mut [u8]) {
+        let frame_buffer = match self.cpu.bus.joypad.sgb {
+            Some(ref sgb) => sgb.read_frame_buffer(),
+            None => self.cpu.bus.ppu.read_frame_buffer(),
+        };
+        frame_buffer.write_to_rbga_buffer(&self.palette, rgba_buffer);
+    }
+    pub fn program_counter(&self) -> u16 {
+        self.cpu.program_counter()
     }
-    pub fn updated_frame_buffer(&mut self) -> Option<Vec<u8>> {
-        match self.cpu.bus.ppu.check_and_reset_updated() {
-            true => Some(self.cpu.bus.ppu.read_frame_buffer().to_vec()),
-            false => None
+    pub fn cpu_state(&self) -> CpuState {
+        self.cpu.state()
+    }
+    pub fn read_memory(&self, address: u16) -> u8 {
+        self.cpu.bus.read_byte(address)
+    }
+    pub fn peek_memory(&self, address: u16) -> u8 {
+        self.cpu.bus.peek_byte(address)
+    }
+    pub fn write_memory(&mut self, address: u16, value: u8) {
+        self.cpu.bus.write_byte(address, value)
+    }
+    pub fn has_cartridge_ram(&self) -> bool {
+        self.cpu.bus.cartridge.has_ram()
+    }
+    // Med SGB tegnes skjermen inne i en ramme på 256x224
+    pub fn screen_size(&self) -> (u32, u32) {
+        match self.cpu.bus.joypad.sgb {
+            Some(_) => (SGB_SCREEN_WIDTH, SGB_SCREEN_HEIGHT),
+            None => (SCREEN_WIDTH, SCREEN_HEIGHT),
         }
     }
     pub fn title(&self) -> String {
         self.cpu.bus.cartridge.title()
     }
+    // Under avspilling av en film ignoreres tastetrykk fra brukeren
     pub fn key_down(&mut self, key: JoypadKey) {
-        self.cpu.bus.joypad.key_down(key)
+        if self.playback.is_some() { return }
+        self.keys |= key.mask();
+        self.update_keys()
     }
     pub fn key_up(&mut self, key: JoypadKey) {
-        self.cpu.bus.joypad.key_up(key)
+        if self.playback.is_some() { return }
+        self.keys &= !key.mask();
+        self.update_keys()
+    }
+    // Turbo trykker tasten annenhver periode så lenge den holdes, fra neste VBlank
+    pub fn turbo_down(&mut self, key: JoypadKey) {
+        self.autofire.turbo_down(key)
+    }
+    pub fn turbo_up(&mut self, key: JoypadKey) {
+        self.autofire.turbo_up(key)
+    }
+    pub fn set_turbo_period(&mut self, frames: u32) {
+        self.autofire.set_period(frames)
+    }
+    // Makroen starter ved neste VBlank, og erstatter en som allerede kjører
+    pub fn play_macro(&mut self, input_macro: InputMacro) {
+        self.autofire.play(input_macro)
+    }
+    pub fn record_macro(&mut self) {
+        self.autofire.start_recording()
+    }
+    pub fn stop_macro_recording(&mut self) -> Option<InputMacro> {
+        self.autofire.stop_recording()
+    }
+    pub fn recording_macro(&self) -> bool {
+        self.autofire.recording()
+    }
+    // Sender endringene til joypaden, og tar dem med i filmen som spilles inn
+    fn update_keys(&mut self) {
+        let target = self.keys | self.injected;
+        for key in JoypadKey::ALL {
+            if (target ^ self.pressed) & key.mask() == 0 { continue }
+            let pressed = target & key.mask() != 0;
+            self.record_key(key, pressed);
+            Self::set_joypad_key(&mut self.cpu, key, pressed);
+        }
+        self.pressed = target;
+    }
+    // På ekte maskinvare våkner CPU-en fra STOP bare om tasten er i en valgt rad i P1,
+    // men her vekker alle tastetrykk den, så et spill aldri blir stående
+    fn set_joypad_key(cpu: &mut CPU, key: JoypadKey, pressed: bool) {
+        match pressed {
+            true => {
+                cpu.bus.joypad.key_down(key);
+                cpu.wake_from_stop();
+            }
+            false => cpu.bus.joypad.key_up(key),
+        }
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.cpu.is_stopped()
+    }
+    // Slår av og på om venstre+høyre og opp+ned kan være nede samtidig. Er av som standard
+    pub fn set_allow_opposite_directions(&mut self, allowed: bool) {
+        self.cpu.bus.joypad.set_allow_opposite_directions(allowed)
+    }
+    fn record_key(&mut self, key: JoypadKey, pressed: bool) {
+        if let Some(ref mut movie) = self.recording {
+            movie.events.push(MovieEvent { frame: self.frame, cycle: self.frame_dots, key, pressed });
+        }
+    }
+    pub(crate) fn start_recording(&mut self, movie: Movie) {
+        self.recording = Some(movie);
+    }
+    pub(crate) fn start_playback(&mut self, player: MoviePlayer) {
+        self.playback = Some(player);
+    }
+    pub fn stop_recording(&mut self) -> Option<Movie> {
+        self.recording.take()
+    }
+    pub fn recording_movie(&self) -> bool {
+        self.recording.is_some()
+    }
+    pub fn playing_movie(&self) -> bool {
+        self.playback.is_some()
+    }
+    pub fn frame_count(&self) -> u32 {
+        self.frame
+    }
+    pub fn cheats(&self) -> &Cheats {
+        &self.cpu.bus.cartridge.cheats
+    }
+    pub fn cheats_mut(&mut self) -> &mut Cheats {
+        &mut self.cpu.bus.cartridge.cheats
     }
     pub fn manual_save(&self) {
         self.cpu.bus.cartridge.manual_save()
     }
+    pub fn video_ram_banks(&self) -> usize {
+        self.cpu.bus.ppu.video_ram_banks()
+    }
+    pub fn tile_data_image(&self, palette: &Palette) -> Vec<u8> {
+        self.cpu.bus.ppu.tile_data_image(palette)
+    }
+    pub fn tile_map_image(&self, tile_map: TileMap, palette: &Palette) -> Vec<u8> {
+        self.cpu.bus.ppu.tile_map_image(tile_map, palette)
+    }
+    pub fn oam_entries(&self) -> Vec<Sprite> {
+        self.cpu.bus.ppu.oam_entries()
+    }
+    pub fn oam_image(&self, palette: &Palette) -> Vec<u8> {
+        self.cpu.bus.ppu.oam_image(palette)
+    }
+    pub fn set_layer_mask(&mut self, layer_mask: LayerMask) {
+        self.cpu.bus.ppu.layer_mask = layer_mask
+    }
+    pub fn layer_mask(&self) -> LayerMask {
+        self.cpu.bus.ppu.layer_mask
+    }
 }
\ No newline at end of file
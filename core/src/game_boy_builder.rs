@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::address_bus::AddressBus;
+use crate::battery_save::{BatterySave, CapturingBatterySave, MemoryBatterySave};
+use crate::cartridge::Cartridge;
+use crate::cheats::Cheats;
+use crate::cpu::CPU;
+use crate::game_boy::GameBoy;
+use crate::model::{BootMode, Model};
+use crate::movie::{Movie, MovieBoot, MoviePlayer};
+use crate::palette::Palette;
+use crate::rtc::{Clock, FixedClock, SystemClock};
+use crate::serial::{SerialDevice, StdoutSerial};
+
+// Valg som bytter litt nøyaktighet mot bekvemmelighet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Accuracy {
+    // Maks 10 sprites per linje, som på ekte maskinvare. Av gir mindre flimring
+    pub sprite_limit: bool,
+    // Seriell overføring tar 8 bit à 512 T-sykluser. Av fullfører overføringen med en gang
+    pub serial_timing: bool,
+}
+
+impl Default for Accuracy {
+    fn default() -> Self {
+        Self {
+            sprite_limit: true,
+            serial_timing: true,
+        }
+    }
+}
+
+pub struct GameBoyBuilder {
+    cartridge_data: Vec<u8>,
+    model: Option<Model>,
+    boot_mode: BootMode,
+    palette: Palette,
+    battery_save: Option<Box<dyn BatterySave>>,
+    clock: Box<dyn Clock>,
+    serial_device: Option<Box<dyn SerialDevice>>,
+    accuracy: Accuracy,
+    cheats: Cheats,
+    record_movie: bool,
+    play_movie: Option<Movie>,
+}
+
+impl GameBoyBuilder {
+    pub fn new(cartridge_data: Vec<u8>) -> Self {
+        Self {
+            cartridge_data,
+            model: None,
+            boot_mode: BootMode::default(),
+            palette: Palette::gray(),
+            battery_save: None,
+            clock: Box::new(SystemClock),
+            serial_device: if cfg!(feature = "test") { Some(Box::new(StdoutSerial)) } else { None },
+            accuracy: Accuracy::default(),
+            cheats: Cheats::new(),
+            record_movie: false,
+            play_movie: None,
+        }
+    }
+    // Uten modell velges den ut fra cartridge-headeren
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+    pub fn boot_mode(mut self, boot_mode: BootMode) -> Self {
+        self.boot_mode = boot_mode;
+        self
+    }
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+    pub fn battery_save(mut self, battery_save: Box<dyn BatterySave>) -> Self {
+        self.battery_save = Some(battery_save);
+        self
+    }
+    pub fn clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    pub fn serial_device(mut self, serial_device: Box<dyn SerialDevice>) -> Self {
+        self.serial_device = Some(serial_device);
+        self
+    }
+    pub fn accuracy(mut self, accuracy: Accuracy) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+    // Juksekodene som er på fra start. De lagres i filmer som spilles inn
+    pub fn cheats(mut self, cheats: Cheats) -> Self {
+        self.cheats = cheats;
+        self
+    }
+    // Spiller inn alle tastetrykk. RTC-en står stille under innspillingen. Hentes med GameBoy::stop_recording
+    pub fn record_movie(mut self) -> Self {
+        self.record_movie = true;
+        self
+    }
+    // Modell, klokke, nøyaktighetsvalg, juksekoder og SRAM tas fra filmen, og lagringer går ikke til disk.
+    // En film spilt inn med ekstern boot-ROM krever samme boot-ROM her
+    pub fn play_movie(mut self, movie: Movie) -> Self {
+        self.play_movie = Some(movie);
+        self
+    }
+    pub fn build(mut self) -> Result<Box<GameBoy>, &'static str> {
+        let mut recording = None;
+        if let Some(ref movie) = self.play_movie {
+            if crc32fast::hash(&self.cartridge_data) != movie.rom_crc32 {
+                return Err("Filmen er spilt inn med en annen ROM");
+            }
+            self.boot_mode = match (movie.boot, self.boot_mode) {
+                (MovieBoot::Embedded, _) => BootMode::Embedded,
+                (MovieBoot::Skip, _) => BootMode::Skip,
+                (MovieBoot::External(crc32), BootMode::External(data)) if crc32fast::hash(&data) == crc32 => BootMode::External(data),
+                (MovieBoot::External(_), _) => return Err("Filmen er spilt inn med en annen boot-ROM"),
+            };
+            self.model = Some(movie.model);
+            self.accuracy = movie.accuracy;
+            self.cheats = movie.cheats.clone();
+            self.clock = Box::new(FixedClock(movie.clock_time));
+            self.battery_save = Some(Box::new(MemoryBatterySave::new(movie.sram.clone())));
+        } else if self.record_movie {
+            let clock_time = self.clock.now();
+            self.clock = Box::new(FixedClock(clock_time));
+            let loaded = Rc::new(RefCell::new(vec![]));
+            if let Some(inner) = self.battery_save.take() {
+                self.battery_save = Some(Box::new(CapturingBatterySave { inner, loaded: loaded.clone() }));
+            }
+            let boot = match self.boot_mode {
+                BootMode::Embedded => MovieBoot::Embedded,
+                BootMode::External(ref data) => MovieBoot::External(crc32fast::hash(data)),
+                BootMode::Skip => MovieBoot::Skip,
+            };
+            recording = Some((crc32fast::hash(&self.cartridge_data), boot, clock_time, loaded));
+        }
+
+        let mut cartridge = Cartridge::from_bytes(self.cartridge_data, self.battery_save, self.clock)?;
+        cartridge.cheats = self.cheats.clone();
+        let model = self.model.unwrap_or_else(|| Model::for_cartridge(&cartridge));
+        let mut bus = AddressBus::new(cartridge, model, &self.boot_mode, self.serial_device)?;
+        bus.ppu.sprite_limit = self.accuracy.sprite_limit;
+        bus.serial.instant = !self.accuracy.serial_timing;
+
+        let mut game_boy = GameBoy::new_with_cpu(CPU::new(bus), self.palette);
+        if let Some(movie) = self.play_movie {
+            game_boy.start_playback(MoviePlayer::new(movie));
+        }
+        if let Some((rom_crc32, boot, clock_time, loaded)) = recording {
+            let mut movie = Movie::new(rom_crc32, model, boot, clock_time, loaded.take());
+            movie.accuracy = self.accuracy;
+            movie.cheats = self.cheats;
+            game_boy.start_recording(movie);
+        }
+        Ok(Box::new(game_boy))
+    }
+}
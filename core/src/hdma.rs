@@ -0,0 +1,66 @@
+// VRAM-DMA for GBC (HDMA1-HDMA5). Kopierer blokker på 16 byte fra ROM/RAM til VRAM,
+// enten alt på én gang (general purpose) eller én blokk per HBlank.
+#[derive(PartialEq, Default)]
+pub enum HdmaMode {
+    #[default]
+    Inactive,
+    GeneralPurpose,
+    HorizontalBlank,
+}
+
+#[derive(Default)]
+pub struct Hdma {
+    source: u16,
+    destination: u16,
+    remaining_blocks: u8,
+    pub mode: HdmaMode,
+}
+
+impl Hdma {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn read_byte(&self, address: u8) -> u8 {
+        match address {
+            0x55 => {
+                let active = if self.mode == HdmaMode::Inactive { 0x80 } else { 0 };
+                active | (self.remaining_blocks.wrapping_sub(1) & 0x7f)
+            }
+            _ => 0xff, // HDMA1-HDMA4 er write-only
+        }
+    }
+    pub fn write_byte(&mut self, address: u8, value: u8) {
+        match address {
+            0x51 => self.source = (self.source & 0x00ff) | (value as u16) << 8,
+            0x52 => self.source = (self.source & 0xff00) | (value & 0xf0) as u16,
+            0x53 => self.destination = (self.destination & 0x00ff) | ((value & 0x1f) as u16) << 8,
+            0x54 => self.destination = (self.destination & 0xff00) | (value & 0xf0) as u16,
+            0x55 => {
+                if self.mode == HdmaMode::HorizontalBlank && value & 0x80 == 0 {
+                    // Avbryter en pågående HBlank-overføring
+                    self.mode = HdmaMode::Inactive;
+                    return
+                }
+                self.remaining_blocks = (value & 0x7f) + 1;
+                self.mode = match value & 0x80 {
+                    0 => HdmaMode::GeneralPurpose,
+                    _ => HdmaMode::HorizontalBlank,
+                };
+            }
+            _ => unreachable!()
+        }
+    }
+    // Gir kilde- og VRAM-adresse for neste blokk på 16 byte
+    pub fn next_block(&mut self) -> Option<(u16, u16)> {
+        if self.mode == HdmaMode::Inactive { return None }
+
+        let block = (self.source, 0x8000 | self.destination);
+        self.source = self.source.wrapping_add(0x10);
+        self.destination = (self.destination + 0x10) & 0x1ff0;
+        self.remaining_blocks -= 1;
+        if self.remaining_blocks == 0 {
+            self.mode = HdmaMode::Inactive;
+        }
+        Some(block)
+    }
+}
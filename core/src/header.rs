@@ -0,0 +1,178 @@
+mod licensee;
+
+use sha1::{Digest, Sha1};
+
+const HEADER_START: usize = 0x0100;
+const HEADER_END: usize = 0x014f;
+
+// Boot-ROM-en sammenligner logoen med denne, og stopper hvis den er feil
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e,
+];
+
+// Headeren i 0x0100-0x014f, som beskriver kassetten
+#[derive(Clone, Debug, PartialEq)]
+pub struct CartridgeHeader {
+    bytes: Vec<u8>,
+}
+
+impl CartridgeHeader {
+    pub fn new(rom: &[u8]) -> Result<Self, &'static str> {
+        let bytes = rom.get(HEADER_START..=HEADER_END).ok_or("ROM-en er for liten til å ha en header")?;
+        Ok(Self { bytes: bytes.to_vec() })
+    }
+    fn byte(&self, address: usize) -> u8 {
+        self.bytes[address - HEADER_START]
+    }
+    fn range(&self, start: usize, end: usize) -> &[u8] {
+        &self.bytes[start - HEADER_START..=end - HEADER_START]
+    }
+    pub fn logo(&self) -> &[u8] {
+        self.range(0x0104, 0x0133)
+    }
+    pub fn logo_valid(&self) -> bool {
+        self.logo() == NINTENDO_LOGO
+    }
+    // Nyere spill bruker de fire siste tegnene i tittelen til produsentkoden
+    pub fn title(&self) -> String {
+        let end = if self.manufacturer_code().is_some() { 0x013e } else { 0x0143 };
+        let title: Vec<u8> = self.range(0x0134, end).iter().copied().take_while(|&byte| byte != 0).collect();
+        String::from_utf8_lossy(&title).trim_end().to_string()
+    }
+    pub fn manufacturer_code(&self) -> Option<String> {
+        let code = self.range(0x013f, 0x0142);
+        (self.cgb_flag() & 0x80 != 0 && code.iter().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit()))
+            .then(|| String::from_utf8_lossy(code).into_owned())
+    }
+    pub fn cgb_flag(&self) -> u8 {
+        self.byte(0x0143)
+    }
+    // 0x80: Spillet støtter GBC, men kjører også på DMG. 0xc0: Kun GBC
+    pub fn cgb_mode(&self) -> bool {
+        matches!(self.cgb_flag(), 0x80 | 0xc0)
+    }
+    pub fn sgb_flag(&self) -> u8 {
+        self.byte(0x0146)
+    }
+    // SGB-funksjoner krever i tillegg at det gamle lisenskodefeltet er 0x33
+    pub fn sgb_support(&self) -> bool {
+        self.sgb_flag() == 0x03 && self.old_licensee_code() == 0x33
+    }
+    pub fn old_licensee_code(&self) -> u8 {
+        self.byte(0x014b)
+    }
+    // Brukes bare når den gamle koden er 0x33
+    pub fn new_licensee_code(&self) -> String {
+        String::from_utf8_lossy(self.range(0x0144, 0x0145)).into_owned()
+    }
+    pub fn licensee(&self) -> Option<&'static str> {
+        match self.old_licensee_code() {
+            0x33 => licensee::new_licensee_name(self.range(0x0144, 0x0145)),
+            code => licensee::old_licensee_name(code),
+        }
+    }
+    pub fn nintendo_licensee(&self) -> bool {
+        match self.old_licensee_code() {
+            0x01 => true,
+            0x33 => self.range(0x0144, 0x0145) == b"01",
+            _ => false,
+        }
+    }
+    pub fn cartridge_type(&self) -> u8 {
+        self.byte(0x0147)
+    }
+    pub fn mapper(&self) -> Option<&'static str> {
+        Some(match self.cartridge_type() {
+            0x00 => "ROM ONLY",
+            0x01 => "MBC1",
+            0x02 => "MBC1+RAM",
+            0x03 => "MBC1+RAM+BATTERY",
+            0x05 => "MBC2",
+            0x06 => "MBC2+BATTERY",
+            0x08 => "ROM+RAM",
+            0x09 => "ROM+RAM+BATTERY",
+            0x0b => "MMM01",
+            0x0c => "MMM01+RAM",
+            0x0d => "MMM01+RAM+BATTERY",
+            0x0f => "MBC3+TIMER+BATTERY",
+            0x10 => "MBC3+TIMER+RAM+BATTERY",
+            0x11 => "MBC3",
+            0x12 => "MBC3+RAM",
+            0x13 => "MBC3+RAM+BATTERY",
+            0x19 => "MBC5",
+            0x1a => "MBC5+RAM",
+            0x1b => "MBC5+RAM+BATTERY",
+            0x1c => "MBC5+RUMBLE",
+            0x1d => "MBC5+RUMBLE+RAM",
+            0x1e => "MBC5+RUMBLE+RAM+BATTERY",
+            0x20 => "MBC6",
+            0x22 => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            0xfc => "POCKET CAMERA",
+            0xfd => "BANDAI TAMA5",
+            0xfe => "HuC3",
+            0xff => "HuC1+RAM+BATTERY",
+            _ => return None,
+        })
+    }
+    // Kassettypene Cartridge har en MBC for
+    pub fn mapper_supported(&self) -> bool {
+        matches!(self.cartridge_type(), 0x00 | 0x01 ..= 0x03 | 0x0f ..= 0x13 | 0x19 ..= 0x1e)
+    }
+    pub fn rom_size_code(&self) -> u8 {
+        self.byte(0x0148)
+    }
+    // 32 KiB ganger 2 opphøyd i koden
+    pub fn rom_size(&self) -> Option<usize> {
+        (self.rom_size_code() <= 0x08).then(|| 0x8000 << self.rom_size_code())
+    }
+    pub fn ram_size_code(&self) -> u8 {
+        self.byte(0x0149)
+    }
+    pub fn ram_size(&self) -> Option<usize> {
+        match self.ram_size_code() {
+            0x00 => Some(0),
+            0x01 => Some(0x800),
+            0x02 => Some(0x2000),
+            0x03 => Some(0x8000),
+            0x04 => Some(0x20000),
+            0x05 => Some(0x10000),
+            _ => None,
+        }
+    }
+    pub fn has_ram(&self) -> bool {
+        self.ram_size_code() != 0
+    }
+    pub fn version(&self) -> u8 {
+        self.byte(0x014c)
+    }
+    pub fn header_checksum(&self) -> u8 {
+        self.byte(0x014d)
+    }
+    // Boot-ROM-en regner ut denne over 0x0134-0x014c, og stopper hvis den ikke stemmer
+    pub fn computed_header_checksum(&self) -> u8 {
+        self.range(0x0134, 0x014c).iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1))
+    }
+    pub fn header_checksum_valid(&self) -> bool {
+        self.header_checksum() == self.computed_header_checksum()
+    }
+    pub fn title_checksum(&self) -> u8 {
+        self.range(0x0134, 0x0143).iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+    }
+    // Big endian, i motsetning til resten av Game Boy. Sjekkes ikke av maskinvaren
+    pub fn global_checksum(&self) -> u16 {
+        u16::from_be_bytes([self.byte(0x014e), self.byte(0x014f)])
+    }
+}
+
+// Summen av alle bytene i ROM-en, unntatt selve den globale sjekksummen
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter().enumerate()
+        .filter(|(address, _)| !matches!(address, 0x014e | 0x014f))
+        .fold(0u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16))
+}
+
+pub fn sha1_hex(rom: &[u8]) -> String {
+    Sha1::digest(rom).iter().map(|byte| format!("{:02X}", byte)).collect()
+}
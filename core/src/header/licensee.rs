@@ -0,0 +1,223 @@
+// Lisensinnehaverne fra Pan Docs. Kodene som ikke står her gir None
+
+pub fn old_licensee_name(code: u8) -> Option<&'static str> {
+    Some(match code {
+        0x00 => "Ingen",
+        0x01 => "Nintendo",
+        0x08 => "Capcom",
+        0x09 => "Hot-B",
+        0x0a => "Jaleco",
+        0x0b => "Coconuts",
+        0x0c => "Elite Systems",
+        0x13 => "Electronic Arts",
+        0x18 => "Hudson Soft",
+        0x19 => "ITC Entertainment",
+        0x1a => "Yanoman",
+        0x1d => "Clary",
+        0x1f => "Virgin",
+        0x24 => "PCM Complete",
+        0x25 => "San-X",
+        0x28 => "Kotobuki Systems",
+        0x29 => "Seta",
+        0x30 => "Infogrames",
+        0x31 => "Nintendo",
+        0x32 => "Bandai",
+        0x34 => "Konami",
+        0x35 => "Hector",
+        0x38 => "Capcom",
+        0x39 => "Banpresto",
+        0x3c => "Entertainment i",
+        0x3e => "Gremlin",
+        0x41 => "Ubi Soft",
+        0x42 => "Atlus",
+        0x44 => "Malibu",
+        0x46 => "Angel",
+        0x47 => "Spectrum Holobyte",
+        0x49 => "Irem",
+        0x4a => "Virgin",
+        0x4d => "Malibu",
+        0x4f => "U.S. Gold",
+        0x50 => "Absolute",
+        0x51 => "Acclaim",
+        0x52 => "Activision",
+        0x53 => "American Sammy",
+        0x54 => "GameTek",
+        0x55 => "Park Place",
+        0x56 => "LJN",
+        0x57 => "Matchbox",
+        0x59 => "Milton Bradley",
+        0x5a => "Mindscape",
+        0x5b => "Romstar",
+        0x5c => "Naxat Soft",
+        0x5d => "Tradewest",
+        0x60 => "Titus",
+        0x61 => "Virgin",
+        0x67 => "Ocean",
+        0x69 => "Electronic Arts",
+        0x6e => "Elite Systems",
+        0x6f => "Electro Brain",
+        0x70 => "Infogrames",
+        0x71 => "Interplay",
+        0x72 => "Broderbund",
+        0x73 => "Sculptered Soft",
+        0x75 => "The Sales Curve",
+        0x78 => "THQ",
+        0x79 => "Accolade",
+        0x7a => "Triffix Entertainment",
+        0x7c => "MicroProse",
+        0x7f => "Kemco",
+        0x80 => "Misawa Entertainment",
+        0x83 => "LOZC",
+        0x86 => "Tokuma Shoten",
+        0x8b => "Bullet-Proof Software",
+        0x8c => "Vic Tokai",
+        0x8e => "Ape",
+        0x8f => "I'Max",
+        0x91 => "Chunsoft",
+        0x92 => "Video System",
+        0x93 => "Tsubaraya Productions",
+        0x95 => "Varie",
+        0x96 => "Yonezawa/S'pal",
+        0x97 => "Kemco",
+        0x99 => "Arc",
+        0x9a => "Nihon Bussan",
+        0x9b => "Tecmo",
+        0x9c => "Imagineer",
+        0x9d => "Banpresto",
+        0x9f => "Nova",
+        0xa1 => "Hori Electric",
+        0xa2 => "Bandai",
+        0xa4 => "Konami",
+        0xa6 => "Kawada",
+        0xa7 => "Takara",
+        0xa9 => "Technos Japan",
+        0xaa => "Broderbund",
+        0xac => "Toei Animation",
+        0xad => "Toho",
+        0xaf => "Namco",
+        0xb0 => "Acclaim",
+        0xb1 => "ASCII / Nexsoft",
+        0xb2 => "Bandai",
+        0xb4 => "Square Enix",
+        0xb6 => "HAL Laboratory",
+        0xb7 => "SNK",
+        0xb9 => "Pony Canyon",
+        0xba => "Culture Brain",
+        0xbb => "Sunsoft",
+        0xbd => "Sony Imagesoft",
+        0xbf => "Sammy",
+        0xc0 => "Taito",
+        0xc2 => "Kemco",
+        0xc3 => "Square",
+        0xc4 => "Tokuma Shoten",
+        0xc5 => "Data East",
+        0xc6 => "Tonkin House",
+        0xc8 => "Koei",
+        0xc9 => "UFL",
+        0xca => "Ultra",
+        0xcb => "VAP",
+        0xcc => "Use Corporation",
+        0xcd => "Meldac",
+        0xce => "Pony Canyon",
+        0xcf => "Angel",
+        0xd0 => "Taito",
+        0xd1 => "Sofel",
+        0xd2 => "Quest",
+        0xd3 => "Sigma Enterprises",
+        0xd4 => "ASK Kodansha",
+        0xd6 => "Naxat Soft",
+        0xd7 => "Copya System",
+        0xd9 => "Banpresto",
+        0xda => "Tomy",
+        0xdb => "LJN",
+        0xdd => "NCS",
+        0xde => "Human",
+        0xdf => "Altron",
+        0xe0 => "Jaleco",
+        0xe1 => "Towa Chiki",
+        0xe2 => "Yutaka",
+        0xe3 => "Varie",
+        0xe5 => "Epoch",
+        0xe7 => "Athena",
+        0xe8 => "Asmik Ace Entertainment",
+        0xe9 => "Natsume",
+        0xea => "King Records",
+        0xeb => "Atlus",
+        0xec => "Epic/Sony Records",
+        0xee => "IGS",
+        0xf0 => "A Wave",
+        0xf3 => "Extreme Entertainment",
+        0xff => "LJN",
+        _ => return None,
+    })
+}
+
+pub fn new_licensee_name(code: &[u8]) -> Option<&'static str> {
+    Some(match code {
+        b"00" => "Ingen",
+        b"01" => "Nintendo R&D1",
+        b"08" => "Capcom",
+        b"13" => "Electronic Arts",
+        b"18" => "Hudson Soft",
+        b"19" => "b-ai",
+        b"20" => "KSS",
+        b"22" => "Planning Office WADA",
+        b"24" => "PCM Complete",
+        b"25" => "San-X",
+        b"28" => "Kemco",
+        b"29" => "SETA Corporation",
+        b"30" => "Viacom",
+        b"31" => "Nintendo",
+        b"32" => "Bandai",
+        b"33" => "Ocean Software/Acclaim Entertainment",
+        b"34" => "Konami",
+        b"35" => "HectorSoft",
+        b"37" => "Taito",
+        b"38" => "Hudson Soft",
+        b"39" => "Banpresto",
+        b"41" => "Ubi Soft",
+        b"42" => "Atlus",
+        b"44" => "Malibu Interactive",
+        b"46" => "Angel",
+        b"47" => "Bullet-Proof Software",
+        b"49" => "Irem",
+        b"50" => "Absolute",
+        b"51" => "Acclaim Entertainment",
+        b"52" => "Activision",
+        b"53" => "Sammy USA Corporation",
+        b"54" => "Konami",
+        b"55" => "Hi Tech Expressions",
+        b"56" => "LJN",
+        b"57" => "Matchbox",
+        b"58" => "Mattel",
+        b"59" => "Milton Bradley Company",
+        b"60" => "Titus Interactive",
+        b"61" => "Virgin Games",
+        b"64" => "Lucasfilm Games",
+        b"67" => "Ocean Software",
+        b"69" => "Electronic Arts",
+        b"70" => "Infogrames",
+        b"71" => "Interplay Entertainment",
+        b"72" => "Broderbund",
+        b"73" => "Sculptured Software",
+        b"75" => "The Sales Curve Limited",
+        b"78" => "THQ",
+        b"79" => "Accolade",
+        b"80" => "Misawa Entertainment",
+        b"83" => "LOZC",
+        b"86" => "Tokuma Shoten",
+        b"87" => "Tsukuda Original",
+        b"91" => "Chunsoft",
+        b"92" => "Video System",
+        b"93" => "Ocean Software/Acclaim Entertainment",
+        b"95" => "Varie",
+        b"96" => "Yonezawa/S'pal",
+        b"97" => "Kaneko",
+        b"99" => "Pack-In-Video",
+        b"9H" => "Bottom Up",
+        b"A4" => "Konami (Yu-Gi-Oh!)",
+        b"BL" => "MTO",
+        b"DK" => "Kodansha",
+        _ => return None,
+    })
+}
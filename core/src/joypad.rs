@@ -1,4 +1,7 @@
+use crate::sgb::Sgb;
 
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum JoypadKey {
     A,
     B,
@@ -10,63 +13,115 @@ pub enum JoypadKey {
     Down,
 }
 
+impl JoypadKey {
+    pub const ALL: [JoypadKey; 8] = [JoypadKey::A, JoypadKey::B, JoypadKey::Select, JoypadKey::Start,
+        JoypadKey::Right, JoypadKey::Left, JoypadKey::Up, JoypadKey::Down];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        JoypadKey::ALL.into_iter().find(|key| key.name().eq_ignore_ascii_case(name))
+    }
+    // Én bit per tast, i rekkefølgen til ALL
+    pub fn mask(&self) -> u8 {
+        1 << JoypadKey::ALL.iter().position(|key| key == self).unwrap()
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            JoypadKey::A => "A",
+            JoypadKey::B => "B",
+            JoypadKey::Select => "Select",
+            JoypadKey::Start => "Start",
+            JoypadKey::Right => "Right",
+            JoypadKey::Left => "Left",
+            JoypadKey::Up => "Up",
+            JoypadKey::Down => "Down",
+        }
+    }
+}
+
 pub struct Joypad {
     data: u8,
-    action_row: u8,
-    d_pad_row: u8,
+    // Tastene som holdes nede, med bitene fra JoypadKey::mask
+    held: u8,
+    // Den sist trykkede av hvert par motsatte retninger
+    latest_direction: u8,
+    // En ekte styrekors kan ikke trykkes begge veier samtidig. Uten dette vinner siste trykk
+    allow_opposite_directions: bool,
     pub interrupt: u8,
+    pub sgb: Option<Sgb>,
 }
 
+const HORIZONTAL: u8 = 0x30;
+const VERTICAL: u8 = 0xc0;
+
 impl Joypad {
-    pub fn new() -> Self {
+    pub fn new(sgb_mode: bool) -> Self {
         Self {
             data: 0xff,
-            action_row: 0x0f,
-            d_pad_row: 0x0f,
+            held: 0,
+            latest_direction: 0,
+            allow_opposite_directions: false,
             interrupt: 0,
+            sgb: sgb_mode.then(Sgb::new),
         }
     }
     pub fn read_byte(&self) -> u8 {
-        self.data
+        match self.sgb {
+            // Med flere spillere gir SGB-en nummeret på valgt kontroller når ingen rad er valgt
+            Some(ref sgb) if sgb.multiplayer() && self.data & 0x30 == 0x30 => (self.data & 0xf0) | (0x0f - sgb.current_player()),
+            // Vi har bare tastene til første spiller
+            Some(ref sgb) if sgb.current_player() != 0 => self.data | 0x0f,
+            _ => self.data,
+        }
     }
     pub fn write_byte(&mut self, value: u8) {
         self.data = (self.data & 0xcf) | (value & 0x30);
+        if let Some(ref mut sgb) = self.sgb {
+            sgb.write_joypad(value);
+        }
         self.update()
     }
     pub fn key_down(&mut self, key: JoypadKey) {
-        match key {
-            JoypadKey::A      => self.action_row &= !(1 << 0),
-            JoypadKey::B      => self.action_row &= !(1 << 1),
-            JoypadKey::Select => self.action_row &= !(1 << 2),
-            JoypadKey::Start  => self.action_row &= !(1 << 3),
-            JoypadKey::Right  => self.d_pad_row  &= !(1 << 0),
-            JoypadKey::Left   => self.d_pad_row  &= !(1 << 1),
-            JoypadKey::Up     => self.d_pad_row  &= !(1 << 2),
-            JoypadKey::Down   => self.d_pad_row  &= !(1 << 3),
+        self.held |= key.mask();
+        for pair in [HORIZONTAL, VERTICAL] {
+            if pair & key.mask() != 0 {
+                self.latest_direction = (self.latest_direction & !pair) | key.mask();
+            }
         }
-        self.interrupt |= 1 << 4;
         self.update()
     }
     pub fn key_up(&mut self, key: JoypadKey) {
-        match key {
-            JoypadKey::A      => self.action_row |= 1 << 0,
-            JoypadKey::B      => self.action_row |= 1 << 1,
-            JoypadKey::Select => self.action_row |= 1 << 2,
-            JoypadKey::Start  => self.action_row |= 1 << 3,
-            JoypadKey::Right  => self.d_pad_row  |= 1 << 0,
-            JoypadKey::Left   => self.d_pad_row  |= 1 << 1,
-            JoypadKey::Up     => self.d_pad_row  |= 1 << 2,
-            JoypadKey::Down   => self.d_pad_row  |= 1 << 3,
-        }
+        self.held &= !key.mask();
+        self.update()
+    }
+    pub fn set_allow_opposite_directions(&mut self, allowed: bool) {
+        self.allow_opposite_directions = allowed;
         self.update()
     }
+    fn visible_keys(&self) -> u8 {
+        let mut keys = self.held;
+        if !self.allow_opposite_directions {
+            for pair in [HORIZONTAL, VERTICAL] {
+                if keys & pair == pair {
+                    keys &= !(pair & !self.latest_direction);
+                }
+            }
+        }
+        keys
+    }
+    // Interrupten kommer når en av linjene går fra høy til lav, enten av et tastetrykk eller et radvalg
     fn update(&mut self) {
-        self.data &= 0xf0;
+        let before = self.data;
+        let keys = self.visible_keys();
+        // Uten valgt rad leses alle tastene som sluppet
+        self.data = (self.data & 0xf0) | 0x0f;
         if self.data & 0x20 == 0x00 {
-            self.data |= self.action_row & 0x0f;
+            self.data &= !(keys & 0x0f);
         }
         if self.data & 0x10 == 0x00 {
-            self.data |= self.d_pad_row & 0x0f;
+            self.data &= !(keys >> 4);
+        }
+        if before & !self.data & 0x0f != 0 {
+            self.interrupt |= 1 << 4;
         }
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,33 @@
 pub mod cpu;
 pub mod address_bus;
+pub mod bus;
 pub mod mbc;
 pub mod ppu;
 pub mod joypad;
 pub mod bootrom;
 pub mod timer;
+pub mod hdma;
 pub mod game_boy;
+pub mod game_boy_builder;
 pub mod cartridge;
 pub mod frame_buffer;
 pub mod apu;
 pub mod battery_save;
+pub mod palette;
+pub mod sgb;
+pub mod model;
+pub mod rtc;
+pub mod serial;
+pub mod pacing;
+pub mod movie;
+pub mod autofire;
+pub mod cheats;
+pub mod ram_search;
+pub mod patch;
+pub mod archive;
+pub mod header;
 
 pub const SCREEN_WIDTH: u32 = 160;
 pub const SCREEN_HEIGHT: u32 = 144;
+pub const SGB_SCREEN_WIDTH: u32 = sgb::SGB_SCREEN_WIDTH as u32;
+pub const SGB_SCREEN_HEIGHT: u32 = sgb::SGB_SCREEN_HEIGHT as u32;
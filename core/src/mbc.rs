@@ -1,6 +1,7 @@
 pub mod mbc_0;
 pub mod mbc_1;
 pub mod mbc_3;
+pub mod mbc_5;
 
 pub trait MBC {
     fn read_rom(&self, address: u16) -> u8;
@@ -8,4 +9,12 @@ pub trait MBC {
     fn write_rom(&mut self, address: u16, value: u8);
     fn write_ram(&mut self, address: u16, value: u8);
     fn manual_save(&self);
+    // Leser SRAM i valgt bank selv om RAM-en ikke er slått på. Brukes av RAM-søket
+    fn peek_ram(&self, address: u16) -> u8 {
+        self.read_ram(address)
+    }
+    // Skriver til SRAM i banken selv om RAM-en ikke er slått på. Brukes av GameShark-koder med bank
+    fn poke_ram(&mut self, _bank: u8, address: u16, value: u8) {
+        self.write_ram(address, value)
+    }
 }
@@ -54,12 +54,12 @@ impl MBC for MBC1 {
                 BankingMode::Simple => self.rom[address as usize],
                 BankingMode::Advanced => {
                     let bank_number = self.rom_bank_number & 0b11100000;
-                    self.rom[(bank_number * 0x4000) | address as usize]
+                    self.rom[((bank_number * 0x4000) | address as usize) % self.rom.len()]
                 }
             },
             0x4000..=0x7fff => {
                 let bank_number = self.rom_bank_number & 0b00011111;
-                self.rom[(bank_number * 0x4000) | (address & 0x3fff) as usize]
+                self.rom[((bank_number * 0x4000) | (address & 0x3fff) as usize) % self.rom.len()]
             }
             _ => panic!("Invalid ROM address"),
         }
@@ -67,12 +67,21 @@ impl MBC for MBC1 {
     fn read_ram(&self, address: u16) -> u8 {
         if !self.ram_enable { return 0xff; }
 
+        self.peek_ram(address)
+    }
+    fn peek_ram(&self, address: u16) -> u8 {
         let bank_number = match self.banking_mode_select {
             BankingMode::Simple => 0,
             BankingMode::Advanced => self.ram_bank_number & 0b00000011,
         };
         self.ram[(bank_number * 0x2000) | (address & 0x1fff) as usize]
     }
+    fn poke_ram(&mut self, bank: u8, address: u16, value: u8) {
+        if self.ram.is_empty() { return }
+
+        let ram_address = (0x2000 * bank as usize + (address as usize & 0x1fff)) % self.ram.len();
+        self.ram[ram_address] = value;
+    }
     fn write_rom(&mut self, address: u16, value: u8) {
         match address {
             0x0000 ..= 0x1fff => self.ram_enable = matches!(value & 0x0f, 0x0a),
@@ -1,5 +1,6 @@
 use crate::battery_save::BatterySave;
 use crate::mbc::MBC;
+use crate::rtc::{Clock, Rtc, RTC_STATE_SIZE};
 
 pub struct MBC3 {
     rom: Vec<u8>,
@@ -9,22 +10,34 @@ pub struct MBC3 {
     ram_enable: bool,
     rom_bank_number: usize,
     ram_bank_number: usize,
+    rtc: Option<Rtc>,
     battery_save: Option<Box<dyn BatterySave>>,
 }
 
 impl MBC3 {
-    pub fn new(data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>) -> Self {
+    pub fn new(data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>, clock: Box<dyn Clock>) -> Self {
         let rom_banks = 64;
         let ram_banks = 4;
         let ram_size = ram_banks * 0x2000;
-        let has_battery = data[0x147] == 0x10 || data[0x147] == 0x13;
+        let has_battery = matches!(data[0x147], 0x0f | 0x10 | 0x13);
+        let mut rtc = matches!(data[0x147], 0x0f | 0x10).then(|| Rtc::new(clock));
         
         Self {
             rom: data,
             ram: {
                 let mut ram = vec![0; ram_size];
                 if has_battery && let Some(ref battery_save) = battery_save {
-                    battery_save.load(&mut ram);
+                    match rtc {
+                        // Lagringer uten RTC-en etter RAM-en får en ny klokke
+                        Some(ref mut rtc) => {
+                            let mut save = vec![0; ram_size + RTC_STATE_SIZE];
+                            if battery_save.load(&mut save) == save.len() {
+                                rtc.load_state(&save[ram_size..]);
+                            }
+                            ram.copy_from_slice(&save[..ram_size]);
+                        }
+                        None => { battery_save.load(&mut ram); }
+                    }
                 }
                 ram
             },
@@ -33,9 +46,18 @@ impl MBC3 {
             ram_enable: false,
             rom_bank_number: 1,
             ram_bank_number: 0,
+            rtc,
             battery_save: battery_save,
         }
     }
+    fn save(&self) {
+        if let Some(ref battery_save) = self.battery_save {
+            match self.rtc {
+                Some(ref rtc) => battery_save.save(&[self.ram.as_slice(), &rtc.save_state()].concat()),
+                None => battery_save.save(&self.ram),
+            }
+        }
+    }
 }
 
 impl MBC for MBC3 {
@@ -50,12 +72,27 @@ impl MBC for MBC3 {
     fn read_ram(&self, address: u16) -> u8 {
         if !self.ram_enable { return 0xff };
         
-        match address {
-            0xa000..=0xbfff => self.ram[0x2000 * self.ram_bank_number + (address as usize - 0xa000)],
+        match (address, &self.rtc) {
+            (0xa000..=0xbfff, Some(rtc)) if self.ram_bank_number >= 0x08 => rtc.read(self.ram_bank_number as u8),
+            (0xa000..=0xbfff, _) => self.ram[0x2000 * self.ram_bank_number + (address as usize - 0xa000)],
             _ => panic!("Invalid RAM address"),
         }
     }
 
+    fn peek_ram(&self, address: u16) -> u8 {
+        match self.ram_bank_number {
+            0x00..=0x03 => self.ram[0x2000 * self.ram_bank_number + (address as usize & 0x1fff)],
+            _ => 0xff,
+        }
+    }
+
+    fn poke_ram(&mut self, bank: u8, address: u16, value: u8) {
+        if self.ram.is_empty() { return }
+
+        let ram_address = (0x2000 * bank as usize + (address as usize & 0x1fff)) % self.ram.len();
+        self.ram[ram_address] = value;
+    }
+
     fn write_rom(&mut self, address: u16, value: u8) {
         match address {
             0x000..=0x1fff => self.ram_enable = value & 0x0a == 0x0a,
@@ -71,11 +108,11 @@ impl MBC for MBC3 {
                 match value {
                     0x00..=0x03 => self.ram_bank_number = value as usize,
                     0x04..=0x07 => panic!("Invalid ROM address"),
-                    0x08..=0x0c => {} // do nothing, does not support RTC at the moment
+                    0x08..=0x0c if self.rtc.is_some() => self.ram_bank_number = value as usize,
                     _ => {} // invalid RAM bank number, but no worries
                 }
             }
-            0x6000..=0x7fff => {} // RTC Data Latch, RTC not implemented
+            0x6000..=0x7fff => if let Some(ref mut rtc) = self.rtc { rtc.write_latch(value) }
             _ => panic!("Invalid ROM address"),
         }
     }
@@ -83,23 +120,49 @@ impl MBC for MBC3 {
     fn write_ram(&mut self, address: u16, value: u8) {
         if !self.ram_enable { return }
         
-        match address {
-            0xa000..=0xbfff => self.ram[0x2000 * self.ram_bank_number + (address as usize - 0xa000)] = value,
+        match (address, &mut self.rtc) {
+            (0xa000..=0xbfff, Some(rtc)) if self.ram_bank_number >= 0x08 => rtc.write(self.ram_bank_number as u8, value),
+            (0xa000..=0xbfff, _) => self.ram[0x2000 * self.ram_bank_number + (address as usize - 0xa000)] = value,
             _ => panic!("Invalid RAM address"),
         }
     }
 
     fn manual_save(&self) {
-        if let Some(ref battery_save) = self.battery_save {
-            battery_save.save(&self.ram);
-        }
+        self.save()
     }
 }
 
 impl Drop for MBC3 {
     fn drop(&mut self) {
-        if let Some(ref battery_save) = self.battery_save {
-            battery_save.save(&self.ram)
-        }
+        self.save()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battery_save::MemoryBatterySave;
+    use crate::rtc::FixedClock;
+
+    fn mbc(save: Vec<u8>) -> MBC3 {
+        let mut rom = vec![0; 0x8000];
+        rom[0x147] = 0x10;
+        let mut mbc = MBC3::new(rom, Some(Box::new(MemoryBatterySave::new(save))), Box::new(FixedClock(0)));
+        mbc.write_rom(0x0000, 0x0a);
+        mbc.write_rom(0x4000, 0x03);
+        mbc
+    }
+
+    #[test]
+    fn loads_save_without_rtc() {
+        let mut save = vec![0; 0x8000];
+        save[0x6005] = 0x34;
+        assert_eq!(mbc(save).read_ram(0xa005), 0x34);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn loads_save_with_rtc() {
+        let mut save = vec![0; 0x8000 + RTC_STATE_SIZE];
+        save[0x6005] = 0x34;
+        assert_eq!(mbc(save).read_ram(0xa005), 0x34);
+    }
+}
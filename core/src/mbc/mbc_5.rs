@@ -0,0 +1,107 @@
+use crate::battery_save::BatterySave;
+use crate::mbc::MBC;
+
+pub struct MBC5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enable: bool,
+    rom_bank_number: usize,
+    ram_bank_number: usize,
+    battery_save: Option<Box<dyn BatterySave>>,
+}
+
+impl MBC5 {
+    pub fn new(data: Vec<u8>, battery_save: Option<Box<dyn BatterySave>>) -> Self {
+        let ram_banks = match data[0x149] {
+            0x02 => 1,
+            0x03 => 4,
+            0x04 => 16,
+            0x05 => 8,
+            _ => 0,
+        };
+        let ram_size = ram_banks * 0x2000;
+        let has_battery = data[0x147] == 0x1b || data[0x147] == 0x1e;
+
+        Self {
+            rom: data,
+            ram: {
+                let mut ram = vec![0; ram_size];
+                if has_battery && let Some(ref battery_save) = battery_save {
+                    battery_save.load(&mut ram);
+                }
+                ram
+            },
+            ram_enable: false,
+            rom_bank_number: 1,
+            ram_bank_number: 0,
+            battery_save: if has_battery { battery_save } else { None },
+        }
+    }
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if !self.ram_enable || self.ram.is_empty() { return None }
+
+        Some((0x2000 * self.ram_bank_number + (address as usize - 0xa000)) % self.ram.len())
+    }
+}
+
+impl MBC for MBC5 {
+    fn read_rom(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3fff => self.rom[address as usize],
+            // I motsetning til MBC1 og MBC3 kan bank 0 også velges her
+            0x4000..=0x7fff => self.rom[(0x4000 * self.rom_bank_number + (address as usize - 0x4000)) % self.rom.len()],
+            _ => panic!("Invalid ROM address"),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram_address(address) {
+            Some(ram_address) => self.ram[ram_address],
+            None => 0xff,
+        }
+    }
+
+    fn peek_ram(&self, address: u16) -> u8 {
+        if self.ram.is_empty() { return 0xff }
+
+        self.ram[(0x2000 * self.ram_bank_number + (address as usize - 0xa000)) % self.ram.len()]
+    }
+
+    fn poke_ram(&mut self, bank: u8, address: u16, value: u8) {
+        if self.ram.is_empty() { return }
+
+        let ram_address = (0x2000 * bank as usize + (address as usize & 0x1fff)) % self.ram.len();
+        self.ram[ram_address] = value;
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1fff => self.ram_enable = value & 0x0f == 0x0a,
+            0x2000..=0x2fff => self.rom_bank_number = (self.rom_bank_number & 0x100) | value as usize,
+            0x3000..=0x3fff => self.rom_bank_number = (self.rom_bank_number & 0x0ff) | ((value as usize & 0x01) << 8),
+            0x4000..=0x5fff => self.ram_bank_number = (value & 0x0f) as usize,
+            0x6000..=0x7fff => {}
+            _ => panic!("Invalid ROM address"),
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if let Some(ram_address) = self.ram_address(address) {
+            self.ram[ram_address] = value;
+        }
+    }
+
+    fn manual_save(&self) {
+        if let Some(ref battery_save) = self.battery_save {
+            battery_save.save(&self.ram);
+        }
+    }
+}
+
+impl Drop for MBC5 {
+    fn drop(&mut self) {
+        if let Some(ref battery_save) = self.battery_save {
+            battery_save.save(&self.ram)
+        }
+    }
+}
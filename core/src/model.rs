@@ -0,0 +1,55 @@
+use crate::cartridge::Cartridge;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    Dmg0,
+    Dmg,
+    Mgb,
+    Sgb,
+    Cgb,
+}
+
+impl Model {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dmg0" => Some(Model::Dmg0),
+            "dmg" => Some(Model::Dmg),
+            "mgb" => Some(Model::Mgb),
+            "sgb" => Some(Model::Sgb),
+            "cgb" => Some(Model::Cgb),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::Dmg0 => "dmg0",
+            Model::Dmg => "dmg",
+            Model::Mgb => "mgb",
+            Model::Sgb => "sgb",
+            Model::Cgb => "cgb",
+        }
+    }
+    // Velger GBC for spill som støtter det, ellers DMG. SGB må velges med `--model sgb`
+    pub fn for_cartridge(cartridge: &Cartridge) -> Self {
+        if cartridge.cgb_mode() { Model::Cgb } else { Model::Dmg }
+    }
+    pub fn boot_rom_size(&self) -> usize {
+        match self {
+            Model::Cgb => 0x900,
+            _ => 0x100,
+        }
+    }
+}
+
+pub enum BootMode {
+    // Den innebygde boot-ROM-en er for DMG. De andre modellene har ingen innebygd boot-ROM, og starter som med Skip
+    Embedded,
+    External(Vec<u8>),
+    Skip,
+}
+
+impl Default for BootMode {
+    fn default() -> Self {
+        if cfg!(feature = "test") { BootMode::Skip } else { BootMode::Embedded }
+    }
+}
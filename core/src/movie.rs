@@ -0,0 +1,307 @@
+pub mod bk2;
+pub mod vbm;
+
+use std::path::Path;
+use crate::cheats::Cheats;
+use crate::game_boy_builder::Accuracy;
+use crate::joypad::JoypadKey;
+use crate::model::Model;
+
+const MAGIC: &[u8; 4] = b"GBM\x1a";
+const VERSION: u8 = 1;
+const SPRITE_LIMIT: u8 = 1 << 0;
+const SERIAL_TIMING: u8 = 1 << 1;
+
+// Hvordan boot-ROM-en ble kjørt da filmen ble spilt inn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MovieBoot {
+    Embedded,
+    // CRC32 av boot-ROM-filen
+    External(u32),
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MovieFormat {
+    // Vårt eget format
+    Gbm,
+    // BizHawk
+    Bk2,
+    // VisualBoyAdvance
+    Vbm,
+}
+
+impl MovieFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("bk2") => MovieFormat::Bk2,
+            Some("vbm") => MovieFormat::Vbm,
+            _ => MovieFormat::Gbm,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MovieEvent {
+    // Antall VBlank siden start, og dots siden forrige VBlank
+    pub frame: u32,
+    pub cycle: u32,
+    pub key: JoypadKey,
+    pub pressed: bool,
+}
+
+// Alt som trengs for å spille av en kjøring på nytt: ROM, modell, boot, klokke, valg, juksekoder, SRAM og tastetrykk
+#[derive(Clone, Debug)]
+pub struct Movie {
+    pub rom_crc32: u32,
+    pub model: Model,
+    pub boot: MovieBoot,
+    // RTC-en står stille på denne tiden under innspilling og avspilling
+    pub clock_time: u64,
+    pub accuracy: Accuracy,
+    // Juksekodene som var på fra start
+    pub cheats: Cheats,
+    // Batterilagringen slik den var da spillet startet
+    pub sram: Vec<u8>,
+    pub events: Vec<MovieEvent>,
+}
+
+impl Movie {
+    pub fn new(rom_crc32: u32, model: Model, boot: MovieBoot, clock_time: u64, sram: Vec<u8>) -> Self {
+        Self { rom_crc32, model, boot, clock_time, accuracy: Accuracy::default(), cheats: Cheats::new(), sram, events: vec![] }
+    }
+    // BizHawk- og VBA-filmer har ikke plass til nøyaktighetsvalg og juksekoder
+    pub fn default_settings(&self) -> bool {
+        self.accuracy == Accuracy::default() && !self.cheats.list().iter().any(|cheat| cheat.enabled)
+    }
+    pub fn frames(&self) -> u32 {
+        self.events.last().map_or(0, |event| event.frame + 1)
+    }
+    // Tastene som er nede i hver frame, med én bit per tast i rekkefølgen til JoypadKey::ALL.
+    // Tastetrykk midt i en frame flyttes til starten av framen
+    pub fn frame_masks(&self) -> Vec<u8> {
+        let mut masks = Vec::with_capacity(self.frames() as usize);
+        let mut mask = 0;
+        let mut events = self.events.iter().peekable();
+        for frame in 0..self.frames() {
+            while let Some(event) = events.next_if(|event| event.frame == frame) {
+                let bit = event.key.mask();
+                match event.pressed {
+                    true => mask |= bit,
+                    false => mask &= !bit,
+                }
+            }
+            masks.push(mask);
+        }
+        masks
+    }
+    // Erstatter tastetrykkene med endringene mellom hver frame
+    pub fn set_frame_masks(&mut self, masks: &[u8]) {
+        self.events.clear();
+        let mut previous = 0;
+        for (frame, &mask) in masks.iter().enumerate() {
+            for (bit, &key) in JoypadKey::ALL.iter().enumerate() {
+                if (mask ^ previous) & (1 << bit) != 0 {
+                    self.events.push(MovieEvent { frame: frame as u32, cycle: 0, key, pressed: mask & (1 << bit) != 0 });
+                }
+            }
+            previous = mask;
+        }
+    }
+    // Leser en film i formatet filendelsen tilsier. ROM-en trengs for å importere andre formater
+    pub fn import(path: &Path, bytes: &[u8], rom: &[u8]) -> Result<Self, &'static str> {
+        match MovieFormat::from_path(path) {
+            MovieFormat::Gbm => Self::from_bytes(bytes),
+            MovieFormat::Bk2 => bk2::import(bytes, rom),
+            MovieFormat::Vbm => vbm::import(bytes, rom),
+        }
+    }
+    pub fn export(&self, path: &Path, rom: &[u8]) -> Result<Vec<u8>, &'static str> {
+        match MovieFormat::from_path(path) {
+            MovieFormat::Gbm => Ok(self.to_bytes()),
+            _ if !self.default_settings() => Err("Bare .gbm-filmer kan lagre juksekoder og nøyaktighetsvalg"),
+            MovieFormat::Bk2 => bk2::export(self, rom),
+            MovieFormat::Vbm => Ok(vbm::export(self, rom)),
+        }
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(self.rom_crc32.to_le_bytes());
+        let model = self.model.name().as_bytes();
+        bytes.push(model.len() as u8);
+        bytes.extend(model);
+        let (boot_kind, boot_crc32) = match self.boot {
+            MovieBoot::Embedded => (0, 0),
+            MovieBoot::External(crc32) => (1, crc32),
+            MovieBoot::Skip => (2, 0),
+        };
+        bytes.push(boot_kind);
+        bytes.extend(boot_crc32.to_le_bytes());
+        bytes.extend(self.clock_time.to_le_bytes());
+        bytes.push(if self.accuracy.sprite_limit { SPRITE_LIMIT } else { 0 } | if self.accuracy.serial_timing { SERIAL_TIMING } else { 0 });
+        let cheats = self.cheats.to_text();
+        bytes.extend((cheats.len() as u32).to_le_bytes());
+        bytes.extend(cheats.as_bytes());
+        bytes.extend((self.sram.len() as u32).to_le_bytes());
+        bytes.extend(&self.sram);
+        bytes.extend((self.events.len() as u32).to_le_bytes());
+        for event in &self.events {
+            bytes.extend(event.frame.to_le_bytes());
+            bytes.extend(event.cycle.to_le_bytes());
+            bytes.push(JoypadKey::ALL.iter().position(|key| *key == event.key).unwrap() as u8);
+            bytes.push(event.pressed as u8);
+        }
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC { return Err("Ikke en filmfil") }
+        if reader.u8()? != VERSION { return Err("Ukjent versjon av filmfilen") }
+        let rom_crc32 = reader.u32()?;
+        let model_length = reader.u8()? as usize;
+        let model = std::str::from_utf8(reader.take(model_length)?).ok()
+            .and_then(Model::from_name)
+            .ok_or("Ukjent modell i filmfilen")?;
+        let boot = match (reader.u8()?, reader.u32()?) {
+            (0, _) => MovieBoot::Embedded,
+            (1, crc32) => MovieBoot::External(crc32),
+            (2, _) => MovieBoot::Skip,
+            _ => return Err("Ukjent boot-modus i filmfilen"),
+        };
+        let clock_time = reader.u64()?;
+        let flags = reader.u8()?;
+        let accuracy = Accuracy { sprite_limit: flags & SPRITE_LIMIT != 0, serial_timing: flags & SERIAL_TIMING != 0 };
+        let cheats_length = reader.u32()? as usize;
+        let cheats = std::str::from_utf8(reader.take(cheats_length)?).ok()
+            .and_then(|text| Cheats::parse(text).ok())
+            .ok_or("Ugyldige juksekoder i filmfilen")?;
+        let sram_length = reader.u32()? as usize;
+        let sram = reader.take(sram_length)?.to_vec();
+        let event_count = reader.u32()?;
+        let mut events = Vec::new();
+        for _ in 0..event_count {
+            let frame = reader.u32()?;
+            let cycle = reader.u32()?;
+            let key = *JoypadKey::ALL.get(reader.u8()? as usize).ok_or("Ugyldig tast i filmfilen")?;
+            let pressed = reader.u8()? != 0;
+            events.push(MovieEvent { frame, cycle, key, pressed });
+        }
+        Ok(Self { rom_crc32, model, boot, clock_time, accuracy, cheats, sram, events })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], &'static str> {
+        let slice = self.bytes.get(self.position..self.position + length).ok_or("Filmfilen er avkortet")?;
+        self.position += length;
+        Ok(slice)
+    }
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> Result<u64, &'static str> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+pub(crate) struct MoviePlayer {
+    movie: Movie,
+    next: usize,
+}
+
+impl MoviePlayer {
+    pub fn new(movie: Movie) -> Self {
+        Self { movie, next: 0 }
+    }
+    // Neste tastetrykk hvis det skal skje nå eller tidligere
+    pub fn next_due(&mut self, frame: u32, cycle: u32) -> Option<MovieEvent> {
+        let event = *self.movie.events.get(self.next)?;
+        if (event.frame, event.cycle) > (frame, cycle) { return None }
+        self.next += 1;
+        Some(event)
+    }
+    pub fn finished(&self) -> bool {
+        self.next >= self.movie.events.len()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::game_boy::GameBoy;
+
+    // Tastene i hver frame. Siste frame slipper alt, så ingen frames forsvinner på slutten
+    pub const MASKS: [u8; 8] = [0x00, 0x01, 0x03, 0x03, 0x80, 0x00, 0xff, 0x00];
+
+    pub fn rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x13e].copy_from_slice(b"FILMTESTEN");
+        rom[0x14d] = 0x5a;
+        rom
+    }
+
+    pub fn movie(model: Model) -> Movie {
+        let mut movie = Movie::new(crc32fast::hash(&rom()), model, MovieBoot::Embedded, 1234, vec![1, 2, 3]);
+        movie.set_frame_masks(&MASKS);
+        movie
+    }
+
+    #[test]
+    fn frame_masks_round_trip() {
+        assert_eq!(movie(Model::Dmg).frame_masks(), MASKS);
+    }
+
+    #[test]
+    fn gbm_round_trip() {
+        let mut movie = movie(Model::Cgb);
+        movie.boot = MovieBoot::External(0xdeadbeef);
+        movie.events.push(MovieEvent { frame: 8, cycle: 1000, key: JoypadKey::Start, pressed: true });
+        movie.accuracy = Accuracy { sprite_limit: false, serial_timing: true };
+        movie.cheats = Cheats::parse("010980FF Liv\n-00A-17B\n").unwrap();
+        let decoded = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        assert_eq!(decoded.rom_crc32, movie.rom_crc32);
+        assert_eq!(decoded.model, Model::Cgb);
+        assert_eq!(decoded.boot, movie.boot);
+        assert_eq!(decoded.clock_time, 1234);
+        assert_eq!(decoded.accuracy, movie.accuracy);
+        assert_eq!(decoded.cheats.list(), movie.cheats.list());
+        assert_eq!(decoded.sram, movie.sram);
+        assert_eq!(decoded.events, movie.events);
+        assert!(Movie::from_bytes(&movie.to_bytes()[..20]).is_err());
+    }
+
+    #[test]
+    fn only_gbm_stores_settings() {
+        let mut movie = movie(Model::Dmg);
+        movie.cheats = Cheats::parse("-010980FF").unwrap();
+        assert!(movie.export(Path::new("film.bk2"), &rom()).is_ok());
+        movie.cheats.set_enabled(0, true);
+        assert_eq!(movie.export(Path::new("film.vbm"), &rom()).unwrap_err(), "Bare .gbm-filmer kan lagre juksekoder og nøyaktighetsvalg");
+        assert!(movie.export(Path::new("film.gbm"), &rom()).is_ok());
+        movie.cheats = Cheats::new();
+        movie.accuracy.serial_timing = false;
+        assert!(movie.export(Path::new("film.bk2"), &rom()).is_err());
+    }
+
+    #[test]
+    fn playback_uses_recorded_settings() {
+        let cheats = Cheats::parse("010980FF").unwrap();
+        let accuracy = Accuracy { sprite_limit: false, serial_timing: false };
+        let mut game_boy = GameBoy::builder(rom()).cheats(cheats.clone()).accuracy(accuracy).record_movie().build().unwrap();
+        let recorded = game_boy.stop_recording().unwrap();
+        assert_eq!(recorded.accuracy, accuracy);
+        assert_eq!(recorded.cheats.list(), cheats.list());
+
+        let game_boy = GameBoy::builder(rom()).play_movie(recorded).build().unwrap();
+        assert_eq!(game_boy.cheats().list(), cheats.list());
+    }
+}
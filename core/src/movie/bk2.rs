@@ -0,0 +1,137 @@
+use std::io::{Cursor, Read, Write};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+use crate::header::sha1_hex;
+use crate::joypad::JoypadKey;
+use crate::model::Model;
+use crate::movie::{Movie, MovieBoot};
+
+// BizHawk-filmer er zip-filer med en tekstlogg der hver linje er én frame, f.eks. `|U......A.|`
+const BUTTONS: [(&str, char, Option<JoypadKey>); 9] = [
+    ("Up", 'U', Some(JoypadKey::Up)),
+    ("Down", 'D', Some(JoypadKey::Down)),
+    ("Left", 'L', Some(JoypadKey::Left)),
+    ("Right", 'R', Some(JoypadKey::Right)),
+    ("Start", 'S', Some(JoypadKey::Start)),
+    ("Select", 's', Some(JoypadKey::Select)),
+    ("B", 'B', Some(JoypadKey::B)),
+    ("A", 'A', Some(JoypadKey::A)),
+    ("Power", 'P', None),
+];
+
+pub fn import(bk2: &[u8], rom: &[u8]) -> Result<Movie, &'static str> {
+    let mut archive = ZipArchive::new(Cursor::new(bk2)).map_err(|_| "BizHawk-filmen er ikke en gyldig zip-fil")?;
+    let mut read_entry = |name: &str| -> Option<Vec<u8>> {
+        let mut file = archive.by_name(name).ok()?;
+        let mut data = vec![];
+        file.read_to_end(&mut data).ok()?;
+        Some(data)
+    };
+    let input_log = read_entry("Input Log.txt").ok_or("BizHawk-filmen mangler Input Log.txt")?;
+    let header = String::from_utf8_lossy(&read_entry("Header.txt").unwrap_or_default()).to_string();
+    let sram = read_entry("SaveRam").unwrap_or_default();
+
+    let header_value = |key: &str| header.lines()
+        .find_map(|line| line.split_once(' ').filter(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, value)| value.trim()));
+    if let Some(sha1) = header_value("SHA1") && !sha1.eq_ignore_ascii_case(&sha1_hex(rom)) {
+        return Err("BizHawk-filmen er spilt inn med en annen ROM");
+    }
+    if header_value("StartsFromSavestate").is_some_and(|value| value.eq_ignore_ascii_case("true")) {
+        return Err("Filmer som starter fra en savestate støttes ikke");
+    }
+    let model = match header_value("IsCGBMode") {
+        Some("1") => Model::Cgb,
+        Some(_) => Model::Dmg,
+        None if rom.get(0x143).is_some_and(|flag| flag & 0x80 != 0) => Model::Cgb,
+        None => Model::Dmg,
+    };
+
+    let input_log = String::from_utf8_lossy(&input_log);
+    let mut buttons = vec![];
+    let mut masks = vec![];
+    for line in input_log.lines() {
+        if let Some(log_key) = line.strip_prefix("LogKey:") {
+            buttons = log_key.split('|')
+                .map(|name| name.trim_start_matches('#').trim_start_matches("P1 "))
+                .filter(|name| !name.is_empty())
+                .map(|name| BUTTONS.iter().find(|(button, ..)| *button == name).and_then(|(.., key)| *key))
+                .collect();
+        } else if line.starts_with('|') {
+            let mask = line.chars().filter(|c| *c != '|').zip(&buttons)
+                .filter(|(c, _)| *c != '.' && *c != ' ')
+                .filter_map(|(_, key)| *key)
+                .fold(0, |mask, key| mask | key.mask());
+            masks.push(mask);
+        }
+    }
+    if buttons.is_empty() {
+        return Err("BizHawk-filmen mangler LogKey i Input Log.txt");
+    }
+
+    let mut movie = Movie::new(crc32fast::hash(rom), model, MovieBoot::Embedded, 0, sram);
+    movie.set_frame_masks(&masks);
+    Ok(movie)
+}
+
+pub fn export(movie: &Movie, rom: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let title: String = rom.get(0x134..0x144).unwrap_or_default().iter()
+        .take_while(|&&byte| byte != 0)
+        .map(|&byte| byte as char)
+        .collect();
+    let header = format!(
+        "MovieVersion BizHawk v2.0.0\nPlatform GB\nGameName {}\nSHA1 {}\nCore Gambatte\nIsCGBMode {}\nrerecordCount 0\n",
+        title.trim(), sha1_hex(rom), (movie.model == Model::Cgb) as u8);
+
+    let mut input_log = String::from("[Input]\nLogKey:#");
+    for (name, ..) in BUTTONS {
+        input_log.push_str(name);
+        input_log.push('|');
+    }
+    input_log.push('\n');
+    for mask in movie.frame_masks() {
+        input_log.push('|');
+        for (_, mnemonic, key) in BUTTONS {
+            let pressed = key.is_some_and(|key| mask & key.mask() != 0);
+            input_log.push(if pressed { mnemonic } else { '.' });
+        }
+        input_log.push_str("|\n");
+    }
+    input_log.push_str("[/Input]\n");
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let mut entries = vec![("Header.txt", header.into_bytes()), ("Input Log.txt", input_log.into_bytes())];
+    if !movie.sram.is_empty() {
+        entries.push(("SaveRam", movie.sram.clone()));
+    }
+    for (name, data) in entries {
+        zip.start_file(name, SimpleFileOptions::default()).map_err(|_| "Kunne ikke skrive BizHawk-filmen")?;
+        zip.write_all(&data).map_err(|_| "Kunne ikke skrive BizHawk-filmen")?;
+    }
+    Ok(zip.finish().map_err(|_| "Kunne ikke skrive BizHawk-filmen")?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movie::tests::{movie, rom, MASKS};
+
+    #[test]
+    fn round_trip() {
+        for model in [Model::Dmg, Model::Cgb] {
+            let bk2 = export(&movie(model), &rom()).unwrap();
+            let decoded = import(&bk2, &rom()).unwrap();
+            assert_eq!(decoded.frame_masks(), MASKS);
+            assert_eq!(decoded.model, model);
+            assert_eq!(decoded.sram, [1, 2, 3]);
+            assert_eq!(decoded.rom_crc32, crc32fast::hash(&rom()));
+        }
+    }
+
+    #[test]
+    fn rejects_other_rom() {
+        let bk2 = export(&movie(Model::Dmg), &rom()).unwrap();
+        let mut other = rom();
+        other[0x200] = 1;
+        assert_eq!(import(&bk2, &other).unwrap_err(), "BizHawk-filmen er spilt inn med en annen ROM");
+    }
+}
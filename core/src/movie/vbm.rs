@@ -0,0 +1,121 @@
+use crate::model::Model;
+use crate::movie::{Movie, MovieBoot};
+
+// VisualBoyAdvance-filmer: 256 bytes header, eventuelt SRAM, og så 2 byte per frame per kontroller.
+// De laveste 8 bitene har samme rekkefølge som JoypadKey::ALL
+const SIGNATURE: &[u8; 4] = b"VBM\x1a";
+const HEADER_SIZE: usize = 0x100;
+const START_FROM_SNAPSHOT: u8 = 0x01;
+const START_FROM_SRAM: u8 = 0x02;
+const SYSTEM_GBA: u8 = 0x01;
+const SYSTEM_GBC: u8 = 0x02;
+const SYSTEM_SGB: u8 = 0x04;
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn import(vbm: &[u8], rom: &[u8]) -> Result<Movie, &'static str> {
+    if vbm.len() < HEADER_SIZE || &vbm[0..4] != SIGNATURE {
+        return Err("Ikke en VBM-fil");
+    }
+    let uid = u32_at(vbm, 0x08);
+    let frames = u32_at(vbm, 0x0c) as usize;
+    let start_flags = vbm[0x14];
+    let controllers = (vbm[0x15] & 0x0f).count_ones().max(1) as usize;
+    let system_flags = vbm[0x16];
+    let sram_offset = u32_at(vbm, 0x38) as usize;
+    let input_offset = u32_at(vbm, 0x3c) as usize;
+
+    if start_flags & START_FROM_SNAPSHOT != 0 {
+        return Err("Filmer som starter fra en savestate støttes ikke");
+    }
+    if system_flags & SYSTEM_GBA != 0 {
+        return Err("VBM-filmen er for Game Boy Advance");
+    }
+    if rom.get(0x14d) != Some(&vbm[0x31]) {
+        return Err("VBM-filmen er spilt inn med en annen ROM");
+    }
+    let model = if system_flags & SYSTEM_GBC != 0 {
+        Model::Cgb
+    } else if system_flags & SYSTEM_SGB != 0 {
+        Model::Sgb
+    } else {
+        Model::Dmg
+    };
+    let sram = match start_flags & START_FROM_SRAM {
+        0 => vec![],
+        _ => vbm.get(sram_offset..input_offset).ok_or("Ugyldig SRAM i VBM-filmen")?.to_vec(),
+    };
+
+    // Bare første kontroller brukes
+    let input = vbm.get(input_offset..).ok_or("Ugyldig input-offset i VBM-filmen")?;
+    let masks: Vec<u8> = input.chunks_exact(2 * controllers).take(frames).map(|frame| frame[0]).collect();
+
+    let mut movie = Movie::new(crc32fast::hash(rom), model, MovieBoot::Embedded, uid as u64, sram);
+    movie.set_frame_masks(&masks);
+    Ok(movie)
+}
+
+pub fn export(movie: &Movie, rom: &[u8]) -> Vec<u8> {
+    let masks = movie.frame_masks();
+    let mut vbm = vec![0; HEADER_SIZE];
+    vbm[0..4].copy_from_slice(SIGNATURE);
+    vbm[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+    vbm[0x08..0x0c].copy_from_slice(&(movie.clock_time as u32).to_le_bytes());
+    vbm[0x0c..0x10].copy_from_slice(&(masks.len() as u32).to_le_bytes());
+    vbm[0x14] = if movie.sram.is_empty() { 0 } else { START_FROM_SRAM };
+    vbm[0x15] = 0x01;
+    let (system_flags, emulator_type) = match movie.model {
+        Model::Cgb => (SYSTEM_GBC, 1u32),
+        Model::Sgb => (SYSTEM_SGB, 2),
+        _ => (0, 3),
+    };
+    vbm[0x16] = system_flags;
+    vbm[0x20..0x24].copy_from_slice(&emulator_type.to_le_bytes());
+    if let Some(title) = rom.get(0x134..0x140) {
+        vbm[0x24..0x30].copy_from_slice(title);
+    }
+    vbm[0x30] = 1;
+    vbm[0x31] = rom.get(0x14d).copied().unwrap_or(0);
+    let global_checksum = rom.get(0x14e..0x150).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+    vbm[0x32..0x34].copy_from_slice(&global_checksum.to_le_bytes());
+
+    if !movie.sram.is_empty() {
+        vbm[0x38..0x3c].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        vbm.extend(&movie.sram);
+    }
+    let input_offset = vbm.len() as u32;
+    vbm[0x3c..0x40].copy_from_slice(&input_offset.to_le_bytes());
+    for mask in masks {
+        vbm.extend([mask, 0]);
+    }
+    vbm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movie::tests::{movie, rom, MASKS};
+
+    #[test]
+    fn round_trip() {
+        for model in [Model::Dmg, Model::Sgb, Model::Cgb] {
+            let vbm = export(&movie(model), &rom());
+            let decoded = import(&vbm, &rom()).unwrap();
+            assert_eq!(decoded.frame_masks(), MASKS);
+            assert_eq!(decoded.model, model);
+            assert_eq!(decoded.clock_time, 1234);
+            assert_eq!(decoded.sram, [1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn rejects_other_rom() {
+        let vbm = export(&movie(Model::Dmg), &rom());
+        let mut other = rom();
+        other[0x14d] = 0;
+        assert_eq!(import(&vbm, &other).unwrap_err(), "VBM-filmen er spilt inn med en annen ROM");
+        assert_eq!(import(&vbm[..0x80], &rom()).unwrap_err(), "Ikke en VBM-fil");
+    }
+}
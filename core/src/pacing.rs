@@ -0,0 +1,149 @@
+// Én frame er 154 linjer à 456 dots, og klokken går på 4 194 304 Hz. Det gir 59,73 frames i sekundet
+pub const DOTS_PER_FRAME: u32 = 70224;
+pub const CLOCK_SPEED: u32 = 4194304;
+pub const FRAME_RATE: f64 = CLOCK_SPEED as f64 / DOTS_PER_FRAME as f64;
+
+// Hastighetene frontendene blar mellom
+pub const SPEED_MULTIPLIERS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+// Henger emuleringen mer enn dette etter, hopper vi over etterslepet i stedet for å kjøre det inn
+const MAX_CATCH_UP_FRAMES: u64 = 4;
+// I turbo justeres antall frames per kall slik at ett kall tar omtrent så lang tid som en skjermoppdatering
+const TURBO_TARGET_SECONDS: f64 = 1.0 / 60.0;
+const MAX_TURBO_FRAMES: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    Multiplier(f64),
+    // Så fort maskinen klarer
+    Turbo,
+}
+
+// Holder emuleringen i riktig tempo over tid, med hastighet, pause og frame-for-frame.
+// Tiden gis inn i sekunder, slik at hver frontend kan bruke sin egen klokke
+pub struct FramePacer {
+    start: Option<f64>,
+    frames: u64,
+    speed: Speed,
+    paused: bool,
+    frames_to_advance: u32,
+    turbo_frames: u32,
+    last_call: Option<f64>,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            frames: 0,
+            speed: Speed::Multiplier(1.0),
+            paused: false,
+            frames_to_advance: 0,
+            turbo_frames: 1,
+            last_call: None,
+        }
+    }
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        self.restart();
+    }
+    // Neste eller forrige hastighet i SPEED_MULTIPLIERS. Fra turbo går vi til høyeste multiplikator
+    pub fn step_speed(&mut self, faster: bool) {
+        let current = match self.speed {
+            Speed::Multiplier(multiplier) => SPEED_MULTIPLIERS.iter().position(|&m| m >= multiplier).unwrap_or(SPEED_MULTIPLIERS.len() - 1),
+            Speed::Turbo => SPEED_MULTIPLIERS.len() - 1,
+        };
+        let next = match faster {
+            true => (current + 1).min(SPEED_MULTIPLIERS.len() - 1),
+            false => current.saturating_sub(1),
+        };
+        self.set_speed(Speed::Multiplier(SPEED_MULTIPLIERS[next]));
+    }
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.frames_to_advance = 0;
+        self.restart();
+    }
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.paused)
+    }
+    // Kjører én frame neste gang frames_due kalles. Pauser hvis vi ikke allerede står på pause
+    pub fn advance_frame(&mut self) {
+        if !self.paused {
+            self.set_paused(true);
+        }
+        self.frames_to_advance += 1;
+    }
+    fn restart(&mut self) {
+        self.start = None;
+        self.frames = 0;
+        self.last_call = None;
+    }
+    // Antall frames som må emuleres nå. Frontenden trenger bare å tegne etter den siste (frame skip)
+    pub fn frames_due(&mut self, now: f64) -> u32 {
+        if self.paused {
+            return std::mem::take(&mut self.frames_to_advance)
+        }
+        match self.speed {
+            Speed::Multiplier(multiplier) => {
+                let start = *self.start.get_or_insert(now);
+                let target = ((now - start) * FRAME_RATE * multiplier) as u64 + 1;
+                if target > self.frames + MAX_CATCH_UP_FRAMES * multiplier.ceil() as u64 {
+                    self.frames = target - 1;
+                }
+                let due = target.saturating_sub(self.frames);
+                self.frames += due;
+                due as u32
+            }
+            Speed::Turbo => {
+                if let Some(last_call) = self.last_call {
+                    self.turbo_frames = match now - last_call < TURBO_TARGET_SECONDS {
+                        true => (self.turbo_frames + self.turbo_frames / 4 + 1).min(MAX_TURBO_FRAMES),
+                        false => (self.turbo_frames * 3 / 4).max(1),
+                    };
+                }
+                self.last_call = Some(now);
+                self.turbo_frames
+            }
+        }
+    }
+    // Sekunder til neste frame skal emuleres. Kan brukes til å sove mellom frames
+    pub fn time_until_next_frame(&self, now: f64) -> f64 {
+        match (self.paused, self.speed, self.start) {
+            (true, ..) if self.frames_to_advance == 0 => 1.0 / FRAME_RATE,
+            (false, Speed::Multiplier(multiplier), Some(start)) => (start + self.frames as f64 / (FRAME_RATE * multiplier) - now).max(0.0),
+            _ => 0.0,
+        }
+    }
+    // Hvor mye lyden skal tones opp eller ned for å følge hastigheten. None betyr at lyden skal dempes
+    pub fn audio_pitch(&self) -> Option<f64> {
+        match (self.paused, self.speed) {
+            (false, Speed::Multiplier(multiplier)) => Some(multiplier),
+            _ => None,
+        }
+    }
+    // Med lyd som klokke: antall frames som fyller lydbufferet opp til ønsket forsinkelse
+    pub fn frames_due_for_audio(&mut self, queued_samples: usize, sample_rate: u32, latency: f64) -> u32 {
+        if self.paused {
+            return std::mem::take(&mut self.frames_to_advance)
+        }
+        let Some(pitch) = self.audio_pitch() else { return 0 };
+        let samples_per_frame = sample_rate as f64 / (FRAME_RATE * pitch);
+        let missing_samples = (sample_rate as f64 * latency - queued_samples as f64).max(0.0);
+        let due = (missing_samples / samples_per_frame).ceil() as u32;
+        self.frames += due as u64;
+        due
+    }
+}
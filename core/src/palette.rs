@@ -0,0 +1,96 @@
+// Fargene til de fire skyggene, fra lysest til mørkest
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shades(pub [[u8; 3]; 4]);
+
+impl Shades {
+    pub fn rgba(&self, shade: u8) -> [u8; 4] {
+        let [red, green, blue] = self.0[(shade & 0b11) as usize];
+        [red, green, blue, 0xff]
+    }
+}
+
+// Hvert palett-register (BGP, OBP0 og OBP1) kan fargelegges for seg, slik GBC-ens boot-ROM gjør med DMG-spill
+#[derive(Clone, PartialEq, Debug)]
+pub struct Palette {
+    pub name: String,
+    pub background: Shades,
+    pub obj_0: Shades,
+    pub obj_1: Shades,
+}
+
+const GRAY: Shades = Shades([[0xff, 0xff, 0xff], [0xaa, 0xaa, 0xaa], [0x55, 0x55, 0x55], [0x00, 0x00, 0x00]]);
+const DMG_GREEN: Shades = Shades([[0x9b, 0xbc, 0x0f], [0x8b, 0xac, 0x0f], [0x30, 0x62, 0x30], [0x0f, 0x38, 0x0f]]);
+const POCKET: Shades = Shades([[0xc4, 0xcf, 0xa1], [0x8b, 0x95, 0x6d], [0x4d, 0x53, 0x3c], [0x1f, 0x1f, 0x1f]]);
+const LIGHT: Shades = Shades([[0x00, 0xb5, 0x81], [0x00, 0x9a, 0x71], [0x00, 0x69, 0x4a], [0x00, 0x4f, 0x3b]]);
+const KIROKAZE: Shades = Shades([[0xe2, 0xf3, 0xe4], [0x94, 0xe3, 0x44], [0x46, 0x87, 0x8f], [0x33, 0x2c, 0x50]]);
+const ICE_CREAM: Shades = Shades([[0xff, 0xf6, 0xd3], [0xf9, 0xa8, 0x75], [0xeb, 0x6b, 0x6f], [0x7c, 0x3f, 0x58]]);
+const RUSTIC: Shades = Shades([[0xed, 0xb4, 0xa1], [0xa9, 0x68, 0x68], [0x76, 0x44, 0x62], [0x2c, 0x21, 0x37]]);
+const MIST: Shades = Shades([[0xc4, 0xf0, 0xc2], [0x5a, 0xb9, 0xa8], [0x1e, 0x60, 0x6e], [0x2d, 0x1b, 0x00]]);
+const GBC_BROWN: Shades = Shades([[0xff, 0xff, 0xff], [0xff, 0xad, 0x63], [0x84, 0x31, 0x00], [0x00, 0x00, 0x00]]);
+const GBC_RED: Shades = Shades([[0xff, 0xff, 0xff], [0xff, 0x84, 0x84], [0x94, 0x3a, 0x3a], [0x00, 0x00, 0x00]]);
+const GBC_GREEN: Shades = Shades([[0xff, 0xff, 0xff], [0x7b, 0xff, 0x31], [0x00, 0x84, 0x00], [0x00, 0x00, 0x00]]);
+const GBC_BLUE: Shades = Shades([[0xff, 0xff, 0xff], [0x63, 0xa5, 0xff], [0x00, 0x00, 0xff], [0x00, 0x00, 0x00]]);
+
+impl Palette {
+    pub fn new(name: &str, background: Shades, obj_0: Shades, obj_1: Shades) -> Self {
+        Self { name: name.to_string(), background, obj_0, obj_1 }
+    }
+    pub fn uniform(name: &str, shades: Shades) -> Self {
+        Self::new(name, shades, shades, shades)
+    }
+    pub fn gray() -> Self {
+        Self::uniform("Grå", GRAY)
+    }
+    pub fn presets() -> Vec<Self> {
+        vec![
+            Self::gray(),
+            Self::uniform("DMG-grønn", DMG_GREEN),
+            Self::uniform("Pocket", POCKET),
+            Self::uniform("Light", LIGHT),
+            Self::uniform("Kirokaze", KIROKAZE),
+            Self::uniform("Ice Cream", ICE_CREAM),
+            Self::uniform("Rustic", RUSTIC),
+            Self::uniform("Mist", MIST),
+            Self::uniform("GBC brun", GBC_BROWN),
+            Self::new("GBC rød", GBC_RED, GBC_GREEN, GBC_BLUE),
+            Self::new("GBC blå", GBC_BLUE, GBC_RED, GBC_GREEN),
+            Self::new("GBC grønn", GBC_GREEN, GBC_BLUE, GBC_RED),
+        ]
+    }
+    pub fn by_name(name: &str) -> Option<Self> {
+        Self::presets().into_iter().find(|palette| palette.name.eq_ignore_ascii_case(name))
+    }
+    // Én palett per linje: `Navn = RRGGBB RRGGBB RRGGBB RRGGBB`, med 4 farger for alle registre
+    // eller 12 farger for BGP, OBP0 og OBP1 hver for seg. Linjer som starter med # ignoreres.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (name, colors) = line.split_once('=')
+                    .ok_or_else(|| format!("Mangler `=` i palettlinjen `{}`", line))?;
+                Self::from_hex(name.trim(), colors)
+            })
+            .collect()
+    }
+    pub fn from_hex(name: &str, colors: &str) -> Result<Self, String> {
+        let colors = colors.split_whitespace()
+            .map(parse_hex_color)
+            .collect::<Result<Vec<_>, _>>()?;
+        let shades = |start: usize| Shades([colors[start], colors[start + 1], colors[start + 2], colors[start + 3]]);
+
+        match colors.len() {
+            4 => Ok(Self::uniform(name, shades(0))),
+            12 => Ok(Self::new(name, shades(0), shades(4), shades(8))),
+            count => Err(format!("Paletten `{}` har {} farger, men må ha 4 eller 12", name, count)),
+        }
+    }
+}
+
+fn parse_hex_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color.trim_start_matches('#');
+    match (hex.len(), u32::from_str_radix(hex, 16)) {
+        (6, Ok(value)) => Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8]),
+        _ => Err(format!("Ugyldig farge `{}`, forventet RRGGBB", color)),
+    }
+}
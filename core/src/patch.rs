@@ -0,0 +1,174 @@
+pub mod ips;
+pub mod ups;
+pub mod bps;
+
+// Den største Game Boy-ROM-en. Større resultater fra UPS og BPS avvises før minnet settes av
+const MAX_TARGET_SIZE: usize = 0x800000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    pub const ALL: [PatchFormat; 3] = [PatchFormat::Ips, PatchFormat::Ups, PatchFormat::Bps];
+
+    // Formatet leses fra starten av filen, ikke filendelsen
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        match patch.get(..5)? {
+            b"PATCH" => Some(PatchFormat::Ips),
+            [b'U', b'P', b'S', b'1', _] => Some(PatchFormat::Ups),
+            [b'B', b'P', b'S', b'1', _] => Some(PatchFormat::Bps),
+            _ => None,
+        }
+    }
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Ups => "ups",
+            PatchFormat::Bps => "bps",
+        }
+    }
+}
+
+// Gir den patchede ROM-en. Feilmeldingene sier hvilken CRC32 som var ventet for UPS og BPS
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, String> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => ips::apply(patch, rom).map_err(str::to_string),
+        Some(PatchFormat::Ups) => ups::apply(patch, rom),
+        Some(PatchFormat::Bps) => bps::apply(patch, rom),
+        None => Err("Ukjent patchformat. Støtter IPS, UPS og BPS".to_string()),
+    }
+}
+
+// Leser patchfilen fra start til slutt
+pub(crate) struct PatchReader<'a> {
+    bytes: &'a [u8],
+    pub position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    pub fn new(bytes: &'a [u8], position: usize) -> Self {
+        Self { bytes, position }
+    }
+    pub fn u8(&mut self) -> Result<u8, &'static str> {
+        let byte = *self.bytes.get(self.position).ok_or("Patchfilen slutter for tidlig")?;
+        self.position += 1;
+        Ok(byte)
+    }
+    pub fn slice(&mut self, length: usize) -> Result<&'a [u8], &'static str> {
+        let slice = self.position.checked_add(length)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or("Patchfilen slutter for tidlig")?;
+        self.position += length;
+        Ok(slice)
+    }
+    pub fn big_endian(&mut self, length: usize) -> Result<usize, &'static str> {
+        Ok(self.slice(length)?.iter().fold(0, |value, byte| value << 8 | *byte as usize))
+    }
+    pub fn target_size(&mut self) -> Result<usize, &'static str> {
+        let size = self.number()?;
+        if size > MAX_TARGET_SIZE { return Err("Patchen gir en ROM som er større enn 8 MiB") }
+        Ok(size)
+    }
+    // Tallformatet til UPS og BPS: 7 bit per byte, der høyeste bit markerer siste byte
+    pub fn number(&mut self) -> Result<usize, &'static str> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.u8()?;
+            value = value.checked_add((byte & 0x7f) as usize * shift).ok_or("Ugyldig tall i patchfilen")?;
+            if byte & 0x80 != 0 { return Ok(value) }
+            shift = shift.checked_mul(0x80).ok_or("Ugyldig tall i patchfilen")?;
+            value = value.checked_add(shift).ok_or("Ugyldig tall i patchfilen")?;
+        }
+    }
+}
+
+// UPS og BPS slutter med CRC32 for kilde, resultat og selve patchen
+pub(crate) struct Checksums {
+    pub source: u32,
+    pub target: u32,
+}
+
+pub(crate) fn read_checksums(patch: &[u8]) -> Result<Checksums, String> {
+    if patch.len() < 12 { return Err("Patchfilen slutter for tidlig".to_string()) }
+    let footer = &patch[patch.len() - 12..];
+    let word = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    if crc32fast::hash(&patch[..patch.len() - 4]) != word(8) {
+        return Err("Patchfilen er skadet (feil CRC32)".to_string())
+    }
+    Ok(Checksums { source: word(0), target: word(4) })
+}
+
+pub(crate) fn check_source(checksums: &Checksums, rom: &[u8]) -> Result<(), String> {
+    let crc = crc32fast::hash(rom);
+    if crc == checksums.source { return Ok(()) }
+    if crc == checksums.target {
+        return Err("ROM-en er allerede patchet".to_string())
+    }
+    Err(format!("Patchen er laget for en ROM med CRC32 {:08x}, men denne ROM-en har {:08x}", checksums.source, crc))
+}
+
+pub(crate) fn check_target(checksums: &Checksums, target: &[u8]) -> Result<(), String> {
+    let crc = crc32fast::hash(target);
+    match crc == checksums.target {
+        true => Ok(()),
+        false => Err(format!("Den patchede ROM-en har CRC32 {:08x}, men patchen venter {:08x}", crc, checksums.target)),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Motsatt av PatchReader::number
+    pub fn number(mut value: usize) -> Vec<u8> {
+        let mut bytes = vec![];
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte | 0x80);
+                return bytes
+            }
+            bytes.push(byte);
+            value -= 1;
+        }
+    }
+
+    // Legger til CRC32 for kilden, resultatet og patchen
+    pub fn with_checksums(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend(crc32fast::hash(source).to_le_bytes());
+        patch.extend(crc32fast::hash(target).to_le_bytes());
+        patch.extend(crc32fast::hash(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn numbers() {
+        for value in [0, 1, 0x7f, 0x80, 0x407f, 0x4080, 0x123456] {
+            let bytes = number(value);
+            assert_eq!(PatchReader::new(&bytes, 0).number(), Ok(value));
+        }
+        assert!(PatchReader::new(&[0x00, 0x01], 0).number().is_err());
+    }
+
+    #[test]
+    fn detects_format() {
+        assert_eq!(PatchFormat::detect(b"PATCH"), Some(PatchFormat::Ips));
+        assert_eq!(PatchFormat::detect(b"UPS1\x00"), Some(PatchFormat::Ups));
+        assert_eq!(PatchFormat::detect(b"BPS1\x00"), Some(PatchFormat::Bps));
+        assert_eq!(PatchFormat::detect(b"BPS1"), None);
+        assert!(apply(b"NOPATCH", &[0; 4]).is_err());
+    }
+
+    #[test]
+    fn rejects_slice_past_end() {
+        let mut reader = PatchReader::new(&[1, 2, 3], 1);
+        assert!(reader.slice(usize::MAX).is_err());
+        assert_eq!(reader.slice(2), Ok(&[2, 3][..]));
+    }
+}
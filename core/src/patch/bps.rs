@@ -0,0 +1,147 @@
+use crate::patch::{check_source, check_target, read_checksums, PatchReader};
+
+const OUT_OF_BOUNDS: &str = "Patchen leser utenfor ROM-en";
+
+// Forskyvningene i SourceCopy og TargetCopy har fortegnet i laveste bit
+fn signed(value: usize) -> isize {
+    match value & 1 {
+        0 => (value >> 1) as isize,
+        _ => -((value >> 1) as isize),
+    }
+}
+
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, String> {
+    let checksums = read_checksums(patch)?;
+    check_source(&checksums, rom)?;
+    let body = &patch[..patch.len() - 12];
+    let mut reader = PatchReader::new(body, 4);
+    let _source_size = reader.number()?;
+    let target_size = reader.target_size()?;
+    let metadata_size = reader.number()?;
+    reader.slice(metadata_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0isize;
+    let mut target_offset = 0isize;
+    while reader.position < body.len() {
+        let action = reader.number()?;
+        let length = (action >> 2) + 1;
+        if length > target_size - target.len() {
+            return Err(format!("Patchen gir mer enn {} bytes", target_size))
+        }
+        match action & 3 {
+            // SourceRead: samme plass i kilden som i resultatet
+            0 => {
+                let start = target.len();
+                target.extend_from_slice(rom.get(start..start + length).ok_or(OUT_OF_BOUNDS)?);
+            }
+            // TargetRead: bytes rett fra patchen
+            1 => target.extend_from_slice(reader.slice(length)?),
+            // SourceCopy
+            2 => {
+                source_offset = source_offset.checked_add(signed(reader.number()?)).ok_or(OUT_OF_BOUNDS)?;
+                let start = usize::try_from(source_offset).map_err(|_| OUT_OF_BOUNDS)?;
+                target.extend_from_slice(start.checked_add(length).and_then(|end| rom.get(start..end)).ok_or(OUT_OF_BOUNDS)?);
+                source_offset += length as isize;
+            }
+            // TargetCopy: kan overlappe det som skrives, så den kopieres én byte om gangen
+            _ => {
+                target_offset = target_offset.checked_add(signed(reader.number()?)).ok_or(OUT_OF_BOUNDS)?;
+                for _ in 0..length {
+                    let index = usize::try_from(target_offset).map_err(|_| OUT_OF_BOUNDS)?;
+                    let byte = *target.get(index).ok_or(OUT_OF_BOUNDS)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if target.len() != target_size {
+        return Err(format!("Patchen ga {} bytes, men skulle gi {}", target.len(), target_size))
+    }
+    check_target(&checksums, &target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::tests::{number, with_checksums};
+
+    const SOURCE: &[u8] = b"ABCDEFGH";
+    const TARGET: &[u8] = b"AEFGHABCxyxyxyAE";
+
+    fn action(kind: usize, length: usize) -> Vec<u8> {
+        number((length - 1) << 2 | kind)
+    }
+
+    fn offset(value: isize) -> Vec<u8> {
+        number((value.unsigned_abs() << 1) | (value < 0) as usize)
+    }
+
+    fn patch(actions: &[Vec<u8>], source: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(number(source.len()));
+        patch.extend(number(target.len()));
+        patch.extend(number(0));
+        patch.extend(actions.concat());
+        with_checksums(patch, source, target)
+    }
+
+    #[test]
+    fn all_actions() {
+        let actions = [
+            // SourceRead: A
+            action(0, 1),
+            // SourceCopy fra 4: EFGH
+            action(2, 4), offset(4),
+            // SourceCopy tilbake til 0: ABC
+            action(2, 3), offset(-8),
+            // TargetRead: xy
+            action(1, 2), b"xy".to_vec(),
+            // TargetCopy som overlapper det som skrives: xyxy
+            action(3, 4), offset(8),
+            // TargetCopy tilbake til starten: AE
+            action(3, 2), offset(-12),
+        ];
+        assert_eq!(apply(&patch(&actions, SOURCE, TARGET), SOURCE), Ok(TARGET.to_vec()));
+    }
+
+    #[test]
+    fn rejects_copy_before_start() {
+        let actions = [action(2, 1), offset(-1)];
+        assert_eq!(apply(&patch(&actions, SOURCE, b"A"), SOURCE), Err(OUT_OF_BOUNDS.to_string()));
+        let actions = [action(0, 1), action(3, 1), offset(-1)];
+        assert_eq!(apply(&patch(&actions, SOURCE, b"AA"), SOURCE), Err(OUT_OF_BOUNDS.to_string()));
+    }
+
+    #[test]
+    fn rejects_wrong_target() {
+        let actions = [action(0, 2)];
+        let err = apply(&patch(&actions, SOURCE, b"AC"), SOURCE).unwrap_err();
+        assert!(err.contains("CRC32"), "{}", err);
+        let err = apply(&patch(&actions, SOURCE, b"ABC"), SOURCE).unwrap_err();
+        assert!(err.contains("skulle gi 3"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_wrong_source() {
+        let actions = [action(0, 1)];
+        let err = apply(&patch(&actions, SOURCE, b"A"), b"ABCDEFGX").unwrap_err();
+        assert!(err.contains("CRC32"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_oversized_target() {
+        let mut header = b"BPS1".to_vec();
+        header.extend(number(SOURCE.len()));
+        header.extend(number(0x800001));
+        let err = apply(&with_checksums(header, SOURCE, b""), SOURCE).unwrap_err();
+        assert!(err.contains("8 MiB"), "{}", err);
+
+        let actions = [action(1, 1 << 40)];
+        assert_eq!(apply(&patch(&actions, SOURCE, b"A"), SOURCE), Err("Patchen gir mer enn 1 bytes".to_string()));
+        let actions = [action(2, 1), offset(isize::MAX)];
+        assert_eq!(apply(&patch(&actions, SOURCE, b"A"), SOURCE), Err(OUT_OF_BOUNDS.to_string()));
+    }
+}
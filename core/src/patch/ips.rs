@@ -0,0 +1,66 @@
+use crate::patch::PatchReader;
+
+// Poster med adresse (3 byte) og lengde (2 byte), eller gjentatt byte når lengden er 0. Slutter med `EOF`
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut rom = rom.to_vec();
+    let mut reader = PatchReader::new(patch, 5);
+    loop {
+        let offset = reader.slice(3)?;
+        if offset == b"EOF" { break }
+        let offset = offset.iter().fold(0, |value, byte| value << 8 | *byte as usize);
+        let data = match reader.big_endian(2)? {
+            0 => {
+                let length = reader.big_endian(2)?;
+                vec![reader.u8()?; length]
+            }
+            length => reader.slice(length)?.to_vec(),
+        };
+        if rom.len() < offset + data.len() {
+            rom.resize(offset + data.len(), 0);
+        }
+        rom[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    // Noen patcher har den nye lengden på ROM-en etter EOF
+    if let Ok(length) = reader.big_endian(3) {
+        rom.truncate(length);
+    }
+    Ok(rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_rle() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x02, 0x00, 0x02, 0xaa, 0xbb]);
+        // RLE: lengde 0, så antall og byten
+        patch.extend([0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0x11]);
+        patch.extend(b"EOF");
+        assert_eq!(apply(&patch, &[0; 8]), Ok(vec![0, 0, 0xaa, 0xbb, 0, 0x11, 0x11, 0x11]));
+    }
+
+    #[test]
+    fn grows_rom() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x03, 0x00, 0x02, 0x01, 0x02]);
+        patch.extend(b"EOF");
+        assert_eq!(apply(&patch, &[0xff; 2]), Ok(vec![0xff, 0xff, 0, 0x01, 0x02]));
+    }
+
+    #[test]
+    fn truncates_after_eof() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x00, 0x00, 0x01, 0x42]);
+        patch.extend(b"EOF");
+        patch.extend([0x00, 0x00, 0x04]);
+        assert_eq!(apply(&patch, &[0; 8]), Ok(vec![0x42, 0, 0, 0]));
+    }
+
+    #[test]
+    fn rejects_truncated_patch() {
+        assert!(apply(b"PATCH\x00\x00\x02\x00\x04\xaa", &[0; 8]).is_err());
+        assert!(apply(b"PATCH", &[0; 8]).is_err());
+    }
+}
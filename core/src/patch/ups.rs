@@ -0,0 +1,79 @@
+use crate::patch::{check_source, check_target, read_checksums, PatchReader};
+
+// Hver blokk hopper fram et antall bytes og XOR-er resten av blokken inn, til en 0-byte
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, String> {
+    let checksums = read_checksums(patch)?;
+    check_source(&checksums, rom)?;
+    let body = &patch[..patch.len() - 12];
+    let mut reader = PatchReader::new(body, 4);
+    let _source_size = reader.number()?;
+    let target_size = reader.target_size()?;
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+    // Alt etter slutten av resultatet ignoreres, så posisjonen kan stoppe på usize::MAX
+    let mut position = 0usize;
+    while reader.position < body.len() {
+        position = position.saturating_add(reader.number()?);
+        loop {
+            let byte = reader.u8()?;
+            if byte == 0 { break }
+            if let Some(target_byte) = target.get_mut(position) {
+                *target_byte ^= byte;
+            }
+            position = position.saturating_add(1);
+        }
+        position = position.saturating_add(1);
+    }
+    check_target(&checksums, &target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patch::tests::{number, with_checksums};
+
+    const SOURCE: [u8; 4] = [1, 2, 3, 4];
+    const TARGET: [u8; 6] = [1, 7, 3, 4, 5, 6];
+
+    fn patch() -> Vec<u8> {
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(number(SOURCE.len()));
+        patch.extend(number(TARGET.len()));
+        // Hopper over 1 byte og XOR-er 2 ^ 7
+        patch.extend(number(1));
+        patch.extend([2 ^ 7, 0]);
+        // Forbi slutten av kilden XOR-es det med 0
+        patch.extend(number(1));
+        patch.extend([5, 6, 0]);
+        with_checksums(patch, &SOURCE, &TARGET)
+    }
+
+    #[test]
+    fn xor_runs_past_source_end() {
+        assert_eq!(apply(&patch(), &SOURCE), Ok(TARGET.to_vec()));
+    }
+
+    #[test]
+    fn rejects_wrong_source() {
+        let err = apply(&patch(), &[1, 2, 3, 5]).unwrap_err();
+        assert!(err.contains("CRC32"), "{}", err);
+        assert_eq!(apply(&patch(), &TARGET), Err("ROM-en er allerede patchet".to_string()));
+    }
+
+    #[test]
+    fn rejects_damaged_patch() {
+        let mut patch = patch();
+        patch[7] ^= 0xff;
+        assert_eq!(apply(&patch, &SOURCE), Err("Patchfilen er skadet (feil CRC32)".to_string()));
+    }
+
+    #[test]
+    fn rejects_oversized_target() {
+        let mut header = b"UPS1".to_vec();
+        header.extend(number(SOURCE.len()));
+        header.extend(number(0x800001));
+        let err = apply(&with_checksums(header, &SOURCE, &TARGET), &SOURCE).unwrap_err();
+        assert!(err.contains("8 MiB"), "{}", err);
+    }
+}
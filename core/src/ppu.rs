@@ -1,33 +1,45 @@
 mod control;
 mod status;
 mod mode;
-mod sprite;
+mod color_palettes;
+mod tile_attributes;
+pub mod sprite;
+pub mod layer_mask;
+pub mod viewer;
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
 use arrayvec::ArrayVec;
-use itertools::Itertools;
 use log::debug;
+use crate::frame_buffer::{CGB_COLOR, HIGHLIGHTED, OBJ_PALETTE_0, OBJ_PALETTE_1};
+use crate::ppu::color_palettes::ColorPalettes;
 use crate::ppu::control::Control;
+use crate::ppu::layer_mask::{LayerMask, Layers};
 use crate::ppu::mode::Mode;
 use crate::ppu::sprite::{Sprite, SpriteFlags};
 use crate::ppu::status::Status;
+use crate::ppu::tile_attributes::TileAttributes;
 
 const SCREEN_WIDTH: usize = 160;
 const SCREEN_HEIGHT: usize = 144;
-const VIDEO_RAM_SIZE: usize = 0x2000;
+const VIDEO_RAM_BANK_SIZE: usize = 0x2000;
+const VIDEO_RAM_SIZE: usize = 2 * VIDEO_RAM_BANK_SIZE;
 const OAM_SIZE: usize = 160;
 const SCANLINES: u8 = 154;
 
+#[derive(Clone, Copy)]
 pub struct Pixel {
     color: u8,
     palette: u8,
+    source: u16,
     background_priority: bool,
+    tile_priority: bool,
 }
 
 pub struct PPU {
+    cgb_mode: bool,
     video_ram: [u8; VIDEO_RAM_SIZE],
-    frame_buffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    video_ram_bank: usize,
+    frame_buffer: [u16; SCREEN_WIDTH * SCREEN_HEIGHT],
     control: Control,
     status: Status,
     mode: Mode,
@@ -38,19 +50,28 @@ pub struct PPU {
     bg_palette: u8,
     obj_palette_0: u8,
     obj_palette_1: u8,
+    bg_color_palettes: ColorPalettes,
+    obj_color_palettes: ColorPalettes,
+    object_priority_mode: u8,
     window_y_position: u8,
     window_x_position: u8,
     oam: [u8; OAM_SIZE],
     updated: bool,
     t_cycles: u32,
-    sprite_buffer: ArrayVec<Sprite, 10>,
+    sprite_buffer: ArrayVec<Sprite, 40>,
+    pub layer_mask: LayerMask,
+    pub sprite_limit: bool,
+    pub skip_rendering: bool,
     pub interrupt: u8,
+    pub entered_horizontal_blank: bool,
 }
 
 impl PPU {
-    pub fn new() -> Self {
+    pub fn new(cgb_mode: bool) -> Self {
         Self {
+            cgb_mode,
             video_ram: [0; VIDEO_RAM_SIZE],
+            video_ram_bank: 0,
             frame_buffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
             control: Control::from_bits_truncate(0),
             status: Status::from_bits_truncate(0),
@@ -62,13 +83,20 @@ impl PPU {
             bg_palette: 0,
             obj_palette_0: 0,
             obj_palette_1: 0,
+            bg_color_palettes: ColorPalettes::new(),
+            obj_color_palettes: ColorPalettes::new(),
+            object_priority_mode: 0,
             window_y_position: 0,
             window_x_position: 0,
             oam: [0; OAM_SIZE],
             updated: false,
             t_cycles: 0,
             sprite_buffer: ArrayVec::new(),
+            layer_mask: LayerMask::new(),
+            sprite_limit: true,
+            skip_rendering: false,
             interrupt: 0,
+            entered_horizontal_blank: false,
         }
     }
     pub fn read_byte(&self, address: u8) -> u8 {
@@ -85,6 +113,12 @@ impl PPU {
             0x49 => self.obj_palette_1,
             0x4a => self.window_y_position,
             0x4b => self.window_x_position,
+            0x4f => 0xfe | self.video_ram_bank as u8,
+            0x68 => self.bg_color_palettes.read_specification(),
+            0x69 => self.bg_color_palettes.read_data(),
+            0x6a => self.obj_color_palettes.read_specification(),
+            0x6b => self.obj_color_palettes.read_data(),
+            0x6c => 0xfe | self.object_priority_mode,
             _ => unreachable!()
         }
     }
@@ -101,6 +135,12 @@ impl PPU {
             0x49 => self.obj_palette_1 = value,
             0x4a => self.window_y_position = value,
             0x4b => self.window_x_position = value,
+            0x4f => self.video_ram_bank = (value & 0x01) as usize,
+            0x68 => self.bg_color_palettes.write_specification(value),
+            0x69 => self.bg_color_palettes.write_data(value),
+            0x6a => self.obj_color_palettes.write_specification(value),
+            0x6b => self.obj_color_palettes.write_data(value),
+            0x6c => self.object_priority_mode = value & 0x01,
             _ => unreachable!()
         }
     }
@@ -115,7 +155,7 @@ impl PPU {
         }
     }
     fn clear_display(&mut self) {
-        self.frame_buffer.iter_mut().for_each(|value| *value = 0xff);
+        self.frame_buffer.iter_mut().for_each(|value| *value = 0x03);
         self.updated = true;
     }
     fn read_status(&self) -> u8 {
@@ -131,13 +171,13 @@ impl PPU {
     pub fn read_video_ram(&self, address: u16) -> u8 {
         match self.mode {
             Mode::Drawing => 0xff,
-            _ => self.video_ram[address as usize & 0x1FFF]
+            _ => self.video_ram[self.video_ram_bank * VIDEO_RAM_BANK_SIZE + (address as usize & 0x1FFF)]
         }
     }
     pub fn write_video_ram(&mut self, address: u16, value: u8) {
         match self.mode {
             Mode::Drawing => (),
-            _ => self.video_ram[address as usize & 0x1FFF] = value,
+            _ => self.video_ram[self.video_ram_bank * VIDEO_RAM_BANK_SIZE + (address as usize & 0x1FFF)] = value,
         }
     }
     pub fn read_oam(&self, address: u16) -> u8 {
@@ -155,12 +195,15 @@ impl PPU {
     pub fn dma_write_oam(&mut self, address: u16, sprite: u8) {
         self.oam[address as usize] = sprite;
     }
+    pub fn lcd_on(&self) -> bool {
+        self.control.lcd_on()
+    }
     pub fn check_and_reset_updated(&mut self) -> bool {
         let result = self.updated;
         self.updated = false;
         result
     }
-    pub fn read_frame_buffer(&mut self) -> &[u8] {
+    pub fn read_frame_buffer(&self) -> &[u16] {
         &self.frame_buffer
     }
     pub fn cycle(&mut self, t_cycles: u32) {
@@ -179,8 +222,7 @@ impl PPU {
         }
     }
     fn oam_scan(&mut self) {
-        self.sprite_buffer.clear();
-        self.sprite_buffer = self.oam.chunks_exact(4)
+        let mut sprites: ArrayVec<(usize, Sprite), 40> = self.oam.chunks_exact(4)
             .filter_map(|sprite| match sprite {
                 &[y, x, tile_index, flags] => {
                     let y = y.wrapping_sub(16);
@@ -199,11 +241,17 @@ impl PPU {
                 }
                 _ => None
             })
-            .take(10)
+            .take(if self.sprite_limit { 10 } else { 40 })
             .enumerate()
-            .sorted_by(Self::sprite_order())
-            .map(|(_, sprite)| sprite)
             .collect();
+        // Indeksen i OAM er med i sorteringsnøkkelen, så ustabil sortering gir samme rekkefølge som stabil.
+        // GBC prioriterer bare etter indeksen i OAM, med mindre OPRI ber om DMG-prioritet.
+        if !self.cgb_mode || self.object_priority_mode & 0x01 != 0 {
+            sprites.sort_unstable_by(Self::sprite_order());
+        }
+
+        self.sprite_buffer.clear();
+        self.sprite_buffer.extend(sprites.into_iter().map(|(_, sprite)| sprite));
 
         self.t_cycles -= 80;
         self.mode = Mode::Drawing
@@ -215,110 +263,146 @@ impl PPU {
         }
     }
     fn draw(&mut self) {
+        // Ved frame skip går tidsstyringen og avbruddene som vanlig, men ingen piksler tegnes
+        if !self.skip_rendering {
+            self.render_scanline();
+        }
+
+        self.t_cycles -= 172;
+        self.mode = Mode::HorizontalBlank;
+        self.entered_horizontal_blank = true;
+        if self.status.contains(Status::mode_0_int_select) {
+            self.interrupt |= 1 << 1;
+        }
+    }
+    fn render_scanline(&mut self) {
         let line_start = SCREEN_WIDTH * self.scanline as usize;
         let line_end = line_start + SCREEN_WIDTH;
         let mut pixels = [0; SCREEN_WIDTH];
         let mut bg_priority = [false; SCREEN_WIDTH];
+        let mut tile_priority = [false; SCREEN_WIDTH];
+        // På GBC skrur ikke LCDC bit 0 av bakgrunnen, men fjerner bakgrunnens prioritet over sprites
+        let bg_window_enable = self.cgb_mode || self.control.contains(Control::bg_window_enable);
+        let bg_master_priority = !self.cgb_mode || self.control.contains(Control::bg_window_enable);
 
-        if self.control.contains(Control::bg_window_enable) {
-            self.fetch_background_pixels().into_iter()
-                .for_each(|(key, Pixel { color, palette, background_priority })| {
-                    pixels[key] = self.color_from_palette(color, palette);
-                    bg_priority[key] = background_priority;
+        if bg_window_enable && !self.layer_mask.hidden.contains(Layers::background) {
+            self.fetch_background_pixels().into_iter().enumerate()
+                .for_each(|(key, pixel)| if let Some(pixel) = pixel {
+                    pixels[key] = self.layer_color(&pixel, Layers::background);
+                    bg_priority[key] = pixel.background_priority;
+                    tile_priority[key] = pixel.tile_priority;
                 })
         }
-        if self.control.contains(Control::window_enable) && self.control.contains(Control::bg_window_enable) && self.window_y_position <= self.scanline {
-            self.fetch_window_pixels().into_iter()
-                .for_each(|(key, Pixel { color, palette, background_priority })| {
-                    pixels[key] = self.color_from_palette(color, palette);
-                    bg_priority[key] = background_priority;
+        if self.control.contains(Control::window_enable) && bg_window_enable && self.window_y_position <= self.scanline
+            && !self.layer_mask.hidden.contains(Layers::window) {
+            self.fetch_window_pixels().into_iter().enumerate()
+                .for_each(|(key, pixel)| if let Some(pixel) = pixel {
+                    pixels[key] = self.layer_color(&pixel, Layers::window);
+                    bg_priority[key] = pixel.background_priority;
+                    tile_priority[key] = pixel.tile_priority;
                 })
         }
-        if self.control.contains(Control::sprite_enable) {
-            self.fetch_sprites_pixels().into_iter()
-                .for_each(|(key, Pixel { color, palette, background_priority })| {
-                if !(background_priority && bg_priority[key]) {
-                    pixels[key] = self.color_from_palette(color, palette);
-                }
-            })
+        if self.control.contains(Control::sprite_enable) && !self.layer_mask.hidden.contains(Layers::sprites) {
+            self.fetch_sprites_pixels().into_iter().enumerate()
+                .for_each(|(key, pixel)| if let Some(pixel) = pixel
+                    && !(bg_master_priority && bg_priority[key] && (pixel.background_priority || tile_priority[key])) {
+                    pixels[key] = self.layer_color(&pixel, Layers::sprites);
+                })
         }
         
         self.frame_buffer[line_start..line_end].copy_from_slice(&pixels);
         self.updated = true;
-
-        self.t_cycles -= 172;
-        self.mode = Mode::HorizontalBlank;
-        if self.status.contains(Status::mode_0_int_select) {
-            self.interrupt |= 1 << 1;
-        }
     }
-    fn fetch_background_pixels(&self) -> HashMap<usize, Pixel>  {
-        let mut pixels = HashMap::new();
+    fn fetch_background_pixels(&self) -> [Option<Pixel>; SCREEN_WIDTH] {
+        let mut pixels = [None; SCREEN_WIDTH];
         let y = self.scanline.wrapping_add(self.vertical_scroll);
-        let row = (y / 8) as usize;
 
-        for i in 0..SCREEN_WIDTH {
+        for (i, pixel) in pixels.iter_mut().enumerate() {
             let x = (i as u8).wrapping_add(self.horizontal_scroll);
-            let col = (x / 8) as usize;
-
-            let tile_number = self.video_ram[(self.control.bg_map_mask() | (row * 32 + col)) & 0x1fff];
-            let tile_data_base = self.control.tile_data_base_from_tile_number(tile_number);
-            let line = ((y % 8) * 2) as usize;
-            let tile_data_low = self.video_ram[(tile_data_base + line) & 0x1fff];
-            let tile_data_high = self.video_ram[(tile_data_base + line + 1) & 0x1fff];
-            let color = self.pixel_color_from_bits(tile_data_low, tile_data_high, x);
-
-            pixels.insert(i, Pixel {
-                color,
-                palette: self.bg_palette,
-                background_priority: color != 0x00
-            });
+            *pixel = Some(self.fetch_tile_map_pixel(self.control.bg_map_mask(), x, y));
         }
         pixels
     }
+    fn fetch_tile_map_pixel(&self, map_mask: usize, x: u8, y: u8) -> Pixel {
+        let map_address = (map_mask | ((y / 8) as usize * 32 + (x / 8) as usize)) & 0x1fff;
+        let tile_number = self.video_ram[map_address];
+        let attributes = match self.cgb_mode {
+            true => TileAttributes::from_bits_truncate(self.video_ram[VIDEO_RAM_BANK_SIZE + map_address]),
+            false => TileAttributes::empty(),
+        };
+        let bank = if attributes.contains(TileAttributes::bank) { VIDEO_RAM_BANK_SIZE } else { 0 };
+        let tile_data_base = self.control.tile_data_base_from_tile_number(tile_number);
+        let line = match attributes.contains(TileAttributes::y_flip) {
+            false => (y % 8) * 2,
+            true => (7 - y % 8) * 2,
+        } as usize;
+        let x = match attributes.contains(TileAttributes::x_flip) {
+            false => x,
+            true => 7 - x % 8,
+        };
+        let tile_data_low = self.video_ram[bank + ((tile_data_base + line) & 0x1fff)];
+        let tile_data_high = self.video_ram[bank + ((tile_data_base + line + 1) & 0x1fff)];
+        let color = self.pixel_color_from_bits(tile_data_low, tile_data_high, x);
+
+        Pixel {
+            color,
+            palette: match self.cgb_mode {
+                true => (attributes & TileAttributes::palette).bits(),
+                false => self.bg_palette,
+            },
+            source: 0,
+            background_priority: color != 0x00,
+            tile_priority: attributes.contains(TileAttributes::priority),
+        }
+    }
     fn color_from_palette(&self, pixel: u8, palette: u8) -> u8 {
         let pixel_value = pixel & 0b11;
         let shift_amount = pixel_value * 2;
         (palette >> shift_amount) & 0b11
     }
+    fn layer_color(&self, pixel: &Pixel, layer: Layers) -> u16 {
+        let highlighted = self.layer_mask.highlighted.contains(layer);
+        if self.cgb_mode {
+            let color_palettes = if pixel.source == 0 { &self.bg_color_palettes } else { &self.obj_color_palettes };
+            let color = color_palettes.color(pixel.palette, pixel.color);
+            return CGB_COLOR | if highlighted { highlight_rgb555(color) } else { color }
+        }
+
+        let shade = self.color_from_palette(pixel.color, pixel.palette) as u16 | pixel.source;
+        match highlighted {
+            true => shade | HIGHLIGHTED,
+            false => shade,
+        }
+    }
     fn pixel_color_from_bits(&self, tile_data_low: u8, tile_data_high: u8, x: u8) -> u8 {
         // mest signifikante bit i en u8 er pikselen lengst til venstre. Må derfor snu x
         let x_bit = 7 - (x % 8);
         (((tile_data_high >> x_bit) & 1) << 1) | ((tile_data_low >> x_bit) & 1)
     }
-    fn fetch_window_pixels(&self) -> HashMap<usize, Pixel>   {
-        let mut pixels = HashMap::new();
+    fn fetch_window_pixels(&self) -> [Option<Pixel>; SCREEN_WIDTH] {
+        let mut pixels = [None; SCREEN_WIDTH];
         let y = self.scanline - self.window_y_position;
-        let row = (y / 8) as usize;
         let start_x = self.window_x_position.saturating_sub(7) as usize;
 
-        for x in start_x..SCREEN_WIDTH {
-            let col = x / 8;
-            let tile_number = self.video_ram[(self.control.window_map_mask() | (row * 32 + col)) & 0x1fff];
-            let tile_data_base = self.control.tile_data_base_from_tile_number(tile_number);
-            let line = ((y % 8) * 2) as usize;
-            let tile_data_low = self.video_ram[(tile_data_base + line) & 0x1fff];
-            let tile_data_high = self.video_ram[(tile_data_base + line + 1) & 0x1fff];
-            let color = self.pixel_color_from_bits(tile_data_low, tile_data_high, x as u8);
-
-            pixels.insert(x, Pixel {
-                color,
-                palette: self.bg_palette,
-                background_priority: color != 0x00,
-            });
+        for (x, pixel) in pixels.iter_mut().enumerate().skip(start_x) {
+            *pixel = Some(self.fetch_tile_map_pixel(self.control.window_map_mask(), x as u8, y));
         }
 
         pixels
     }
-    fn fetch_sprites_pixels(&self) -> HashMap<usize, Pixel> {
-        let mut pixels = HashMap::new();
+    fn fetch_sprites_pixels(&self) -> [Option<Pixel>; SCREEN_WIDTH] {
+        let mut pixels = [None; SCREEN_WIDTH];
         for sprite in self.sprite_buffer.iter() {
             let tile_line = match sprite.flags.contains(SpriteFlags::y_flip) {
                 false => self.scanline.wrapping_sub(sprite.y),
                 true => self.control.sprite_height() - self.scanline.wrapping_sub(sprite.y) - 1,
             } * 2;
-            let tile_data_low = self.video_ram[(((sprite.tile_index as u16) << 4) | (tile_line as u16)) as usize];
-            let tile_data_high = self.video_ram[(((sprite.tile_index as u16) << 4) | (tile_line as u16 + 1u16)) as usize];
+            let bank = match self.cgb_mode && sprite.flags.contains(SpriteFlags::bank) {
+                true => VIDEO_RAM_BANK_SIZE,
+                false => 0,
+            };
+            let tile_data_low = self.video_ram[bank + (((sprite.tile_index as u16) << 4) | (tile_line as u16)) as usize];
+            let tile_data_high = self.video_ram[bank + (((sprite.tile_index as u16) << 4) | (tile_line as u16 + 1u16)) as usize];
 
             for x in 0..8 {
                 let sprite_x = sprite.x.wrapping_add(x);
@@ -332,12 +416,18 @@ impl PPU {
                 match self.pixel_color_from_bits(tile_data_low, tile_data_high, bit) {
                     0x00 => continue,
                     color => {
-                        let palette = if sprite.flags.contains(SpriteFlags::palette) { self.obj_palette_1 } else { self.obj_palette_0 };
+                        let (palette, source) = match (self.cgb_mode, sprite.flags.contains(SpriteFlags::palette)) {
+                            (true, _) => ((sprite.flags.clone() & SpriteFlags::cgb_palette).bits(), OBJ_PALETTE_0),
+                            (false, true) => (self.obj_palette_1, OBJ_PALETTE_1),
+                            (false, false) => (self.obj_palette_0, OBJ_PALETTE_0),
+                        };
 
-                        pixels.entry(sprite_x as usize).or_insert(Pixel {
+                        pixels[sprite_x as usize].get_or_insert(Pixel {
                             color,
                             palette,
+                            source,
                             background_priority: sprite.flags.contains(SpriteFlags::obj_to_bg_priority),
+                            tile_priority: false,
                         });
                     }
                 }
@@ -376,4 +466,12 @@ impl PPU {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+fn highlight_rgb555(color: u16) -> u16 {
+    // Samme blanding med magenta som for DMG-piksler i FrameBuffer
+    let red = (color & 0x1f) / 2 + 0x10;
+    let green = ((color >> 5) & 0x1f) / 2;
+    let blue = ((color >> 10) & 0x1f) / 2 + 0x10;
+    red | green << 5 | blue << 10
+}
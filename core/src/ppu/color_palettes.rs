@@ -0,0 +1,38 @@
+// Palett-RAM for GBC: 8 paletter à 4 farger, hver farge er to byte RGB555 (little endian)
+const PALETTE_RAM_SIZE: usize = 64;
+
+pub struct ColorPalettes {
+    ram: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl ColorPalettes {
+    pub fn new() -> Self {
+        Self {
+            ram: [0xff; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+    pub fn read_specification(&self) -> u8 {
+        0x40 | if self.auto_increment { 0x80 } else { 0 } | self.index
+    }
+    pub fn write_specification(&mut self, value: u8) {
+        self.auto_increment = value & 0x80 != 0;
+        self.index = value & 0x3f;
+    }
+    pub fn read_data(&self) -> u8 {
+        self.ram[self.index as usize]
+    }
+    pub fn write_data(&mut self, value: u8) {
+        self.ram[self.index as usize] = value;
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3f;
+        }
+    }
+    pub fn color(&self, palette: u8, color: u8) -> u16 {
+        let index = (palette as usize & 0b111) * 8 + (color as usize & 0b11) * 2;
+        (self.ram[index] as u16 | (self.ram[index + 1] as u16) << 8) & 0x7fff
+    }
+}
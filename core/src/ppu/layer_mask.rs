@@ -0,0 +1,26 @@
+use bitflags::bitflags;
+
+bitflags! {
+    #[derive(Clone, Copy, PartialEq, Default)]
+    pub struct Layers: u8 {
+        const background = 1;
+        const window = 1 << 1;
+        const sprites = 1 << 2;
+    }
+}
+
+// Feilsøking: lag som skjules eller fremheves uavhengig av LCDC
+#[derive(Clone, Copy, Default)]
+pub struct LayerMask {
+    pub hidden: Layers,
+    pub highlighted: Layers,
+}
+
+impl LayerMask {
+    pub fn new() -> Self {
+        Self {
+            hidden: Layers::empty(),
+            highlighted: Layers::empty(),
+        }
+    }
+}
@@ -1,16 +1,18 @@
 use bitflags::bitflags;
 
 bitflags! {
-    #[derive(Clone)]
+    #[derive(Clone, Debug)]
     pub struct SpriteFlags: u8 {
         const obj_to_bg_priority = 1 << 7;
         const y_flip = 1 << 6;
         const x_flip = 1 << 5;
         const palette = 1 << 4;
+        const bank = 1 << 3;
+        const cgb_palette = 0b111;
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Sprite {
     pub y: u8,
     pub x: u8,
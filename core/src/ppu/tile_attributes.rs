@@ -0,0 +1,13 @@
+use bitflags::bitflags;
+
+bitflags!(
+    // Ligger i VRAM-bank 1 på samme adresse som tile-nummeret i bakgrunnskartet (kun GBC)
+    #[derive(Clone, Copy)]
+    pub struct TileAttributes: u8 {
+        const priority = 1 << 7;
+        const y_flip = 1 << 6;
+        const x_flip = 1 << 5;
+        const bank = 1 << 3;
+        const palette = 0b111;
+    }
+);
@@ -0,0 +1,204 @@
+use crate::frame_buffer::rgb555_to_rgba;
+use crate::palette::Palette;
+use crate::ppu::{PPU, VIDEO_RAM_BANK_SIZE};
+use crate::ppu::sprite::{Sprite, SpriteFlags};
+use crate::ppu::tile_attributes::TileAttributes;
+
+pub const TILES_PER_ROW: usize = 16;
+pub const TILE_COUNT: usize = 384;
+// Bredden for én VRAM-bank. På GBC vises bank 0 og 1 ved siden av hverandre
+pub const TILE_VIEWER_WIDTH: usize = TILES_PER_ROW * 8;
+pub const TILE_VIEWER_HEIGHT: usize = TILE_COUNT / TILES_PER_ROW * 8;
+pub const TILE_MAP_VIEWER_SIZE: usize = 256;
+pub const OAM_ENTRIES: usize = 40;
+pub const OAM_VIEWER_COLUMNS: usize = 10;
+pub const OAM_VIEWER_CELL_WIDTH: usize = 12;
+pub const OAM_VIEWER_CELL_HEIGHT: usize = 20;
+pub const OAM_VIEWER_WIDTH: usize = OAM_VIEWER_COLUMNS * OAM_VIEWER_CELL_WIDTH;
+pub const OAM_VIEWER_HEIGHT: usize = OAM_ENTRIES / OAM_VIEWER_COLUMNS * OAM_VIEWER_CELL_HEIGHT;
+
+const VIEWPORT_COLOR: [u8; 4] = [0xff, 0x00, 0x00, 0xff];
+const TRANSPARENT_COLOR: [u8; 4] = [0x40, 0x40, 0x80, 0xff];
+
+#[derive(Clone, Copy)]
+pub enum TileMap {
+    Low,  // 0x9800
+    High, // 0x9c00
+}
+
+impl TileMap {
+    fn base(&self) -> usize {
+        match self {
+            TileMap::Low => 0x1800,
+            TileMap::High => 0x1c00,
+        }
+    }
+}
+
+impl PPU {
+    pub fn video_ram_banks(&self) -> usize {
+        if self.cgb_mode { 2 } else { 1 }
+    }
+    // Tiles har ingen egen palett, så på GBC tegnes de med bakgrunnspalett 0
+    pub fn tile_data_image(&self, palette: &Palette) -> Vec<u8> {
+        let width = TILE_VIEWER_WIDTH * self.video_ram_banks();
+        let mut image = vec![0; width * TILE_VIEWER_HEIGHT * 4];
+        for bank in 0..self.video_ram_banks() {
+            for tile in 0..TILE_COUNT {
+                let tile_x = bank * TILE_VIEWER_WIDTH + (tile % TILES_PER_ROW) * 8;
+                let tile_y = (tile / TILES_PER_ROW) * 8;
+                for line in 0..8 {
+                    for x in 0..8 {
+                        let color = self.tile_pixel(bank * VIDEO_RAM_BANK_SIZE, tile * 16, line, x);
+                        let pixel_index = ((tile_y + line) * width + tile_x + x) * 4;
+                        image[pixel_index..pixel_index + 4].copy_from_slice(&self.background_rgba(color, 0, palette));
+                    }
+                }
+            }
+        }
+        image
+    }
+    pub fn tile_map_image(&self, tile_map: TileMap, palette: &Palette) -> Vec<u8> {
+        let mut image = vec![0; TILE_MAP_VIEWER_SIZE * TILE_MAP_VIEWER_SIZE * 4];
+        for y in 0..TILE_MAP_VIEWER_SIZE {
+            for x in 0..TILE_MAP_VIEWER_SIZE {
+                let map_address = tile_map.base() + (y / 8) * 32 + x / 8;
+                let tile_number = self.video_ram[map_address];
+                let attributes = match self.cgb_mode {
+                    true => TileAttributes::from_bits_truncate(self.video_ram[VIDEO_RAM_BANK_SIZE + map_address]),
+                    false => TileAttributes::empty(),
+                };
+                let bank = if attributes.contains(TileAttributes::bank) { VIDEO_RAM_BANK_SIZE } else { 0 };
+                let line = if attributes.contains(TileAttributes::y_flip) { 7 - y % 8 } else { y % 8 };
+                let tile_x = if attributes.contains(TileAttributes::x_flip) { 7 - x % 8 } else { x % 8 };
+                let tile_data_base = self.control.tile_data_base_from_tile_number(tile_number);
+                let color = self.tile_pixel(bank, tile_data_base, line, tile_x);
+                let pixel_index = (y * TILE_MAP_VIEWER_SIZE + x) * 4;
+                image[pixel_index..pixel_index + 4].copy_from_slice(&self.background_rgba(color, (attributes & TileAttributes::palette).bits(), palette));
+            }
+        }
+        self.outline_viewport(&mut image);
+        image
+    }
+    pub fn oam_entries(&self) -> Vec<Sprite> {
+        self.oam.chunks_exact(4)
+            .map(|entry| Sprite {
+                y: entry[0].wrapping_sub(16),
+                x: entry[1].wrapping_sub(8),
+                tile_index: entry[2],
+                flags: SpriteFlags::from_bits_truncate(entry[3]),
+            })
+            .collect()
+    }
+    pub fn oam_image(&self, palette: &Palette) -> Vec<u8> {
+        let mut image = TRANSPARENT_COLOR.repeat(OAM_VIEWER_WIDTH * OAM_VIEWER_HEIGHT);
+        for (i, sprite) in self.oam_entries().iter().enumerate() {
+            let cell_x = (i % OAM_VIEWER_COLUMNS) * OAM_VIEWER_CELL_WIDTH + 2;
+            let cell_y = (i / OAM_VIEWER_COLUMNS) * OAM_VIEWER_CELL_HEIGHT + 2;
+            // I 8x16-modus ignoreres bit 0 i tile-indeksen
+            let tile_index = match self.control.sprite_height() {
+                16 => sprite.tile_index & 0xfe,
+                _ => sprite.tile_index,
+            } as usize;
+            let bank = match self.cgb_mode && sprite.flags.contains(SpriteFlags::bank) {
+                true => VIDEO_RAM_BANK_SIZE,
+                false => 0,
+            };
+            let (obj_palette, shades) = match sprite.flags.contains(SpriteFlags::palette) {
+                true => (self.obj_palette_1, &palette.obj_1),
+                false => (self.obj_palette_0, &palette.obj_0),
+            };
+
+            for line in 0..self.control.sprite_height() as usize {
+                for x in 0..8 {
+                    let color = self.tile_pixel(bank, tile_index * 16, line, x);
+                    if color == 0 { continue }
+                    let rgba = match self.cgb_mode {
+                        true => rgb555_to_rgba(self.obj_color_palettes.color((sprite.flags.clone() & SpriteFlags::cgb_palette).bits(), color)),
+                        false => shades.rgba(self.color_from_palette(color, obj_palette)),
+                    };
+                    let pixel_index = ((cell_y + line) * OAM_VIEWER_WIDTH + cell_x + x) * 4;
+                    image[pixel_index..pixel_index + 4].copy_from_slice(&rgba);
+                }
+            }
+        }
+        image
+    }
+    // bank er starten på VRAM-banken, 0 eller VIDEO_RAM_BANK_SIZE
+    fn tile_pixel(&self, bank: usize, tile_data_base: usize, line: usize, x: usize) -> u8 {
+        let tile_data_low = self.video_ram[bank + ((tile_data_base + line * 2) & 0x1fff)];
+        let tile_data_high = self.video_ram[bank + ((tile_data_base + line * 2 + 1) & 0x1fff)];
+        self.pixel_color_from_bits(tile_data_low, tile_data_high, x as u8)
+    }
+    // GBC bruker palett-RAM-en, DMG BGP-registeret og den valgte paletten
+    fn background_rgba(&self, color: u8, cgb_palette: u8, palette: &Palette) -> [u8; 4] {
+        match self.cgb_mode {
+            true => rgb555_to_rgba(self.bg_color_palettes.color(cgb_palette, color)),
+            false => palette.background.rgba(self.color_from_palette(color, self.bg_palette)),
+        }
+    }
+    fn outline_viewport(&self, image: &mut [u8]) {
+        let mut set_pixel = |x: u8, y: u8| {
+            let pixel_index = (y as usize * TILE_MAP_VIEWER_SIZE + x as usize) * 4;
+            image[pixel_index..pixel_index + 4].copy_from_slice(&VIEWPORT_COLOR);
+        };
+        // Utsnittet kan gå rundt kanten av kartet, derfor wrapping_add
+        for x in 0..crate::SCREEN_WIDTH as u8 {
+            set_pixel(self.horizontal_scroll.wrapping_add(x), self.vertical_scroll);
+            set_pixel(self.horizontal_scroll.wrapping_add(x), self.vertical_scroll.wrapping_add(crate::SCREEN_HEIGHT as u8 - 1));
+        }
+        for y in 0..crate::SCREEN_HEIGHT as u8 {
+            set_pixel(self.horizontal_scroll, self.vertical_scroll.wrapping_add(y));
+            set_pixel(self.horizontal_scroll.wrapping_add(crate::SCREEN_WIDTH as u8 - 1), self.vertical_scroll.wrapping_add(y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [0xff, 0x00, 0x00, 0xff];
+    const WHITE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+    // Tile 0 i bank 1 har farge 1 på linje 1, og bank 0 er tom
+    fn cgb_ppu() -> PPU {
+        let mut ppu = PPU::new(true);
+        ppu.video_ram[VIDEO_RAM_BANK_SIZE + 0x1002] = 0xff;
+        ppu.bg_color_palettes.write_specification(0x80 | (2 * 8 + 2));
+        ppu.bg_color_palettes.write_data(0x1f);
+        ppu.bg_color_palettes.write_data(0x00);
+        ppu
+    }
+
+    fn pixel(image: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+        image[(y * width + x) * 4..][..4].try_into().unwrap()
+    }
+
+    #[test]
+    fn tile_data_shows_both_banks() {
+        assert_eq!(PPU::new(false).tile_data_image(&Palette::gray()).len(), TILE_VIEWER_WIDTH * TILE_VIEWER_HEIGHT * 4);
+
+        let mut ppu = cgb_ppu();
+        ppu.video_ram[VIDEO_RAM_BANK_SIZE + 0x0002] = 0xff;
+        ppu.bg_color_palettes.write_specification(0x80 | 2);
+        ppu.bg_color_palettes.write_data(0x1f);
+        ppu.bg_color_palettes.write_data(0x00);
+        let image = ppu.tile_data_image(&Palette::gray());
+        let width = TILE_VIEWER_WIDTH * 2;
+        assert_eq!(image.len(), width * TILE_VIEWER_HEIGHT * 4);
+        assert_eq!(pixel(&image, width, 0, 1), WHITE);
+        assert_eq!(pixel(&image, width, TILE_VIEWER_WIDTH, 1), RED);
+    }
+
+    #[test]
+    fn tile_map_uses_cgb_attributes() {
+        let mut ppu = cgb_ppu();
+        assert_eq!(pixel(&ppu.tile_map_image(TileMap::Low, &Palette::gray()), TILE_MAP_VIEWER_SIZE, 1, 1), WHITE);
+
+        ppu.video_ram[VIDEO_RAM_BANK_SIZE + 0x1800] = (TileAttributes::bank | TileAttributes::y_flip).bits() | 2;
+        let image = ppu.tile_map_image(TileMap::Low, &Palette::gray());
+        assert_eq!(pixel(&image, TILE_MAP_VIEWER_SIZE, 1, 6), RED);
+        assert_eq!(pixel(&image, TILE_MAP_VIEWER_SIZE, 1, 1), WHITE);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::cheats::Cheat;
+use crate::game_boy::GameBoy;
+
+// Områdene det søkes i: WRAM, HRAM og SRAM i valgte banker
+const WORK_RAM: (u16, u16) = (0xc000, 0xdfff);
+const HIGH_RAM: (u16, u16) = (0xff80, 0xfffe);
+const CARTRIDGE_RAM: (u16, u16) = (0xa000, 0xbfff);
+
+// Hvordan verdiene leses. 16-bits verdier er little endian, og BCD har ett siffer per nibble
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    U8,
+    U16,
+    Bcd8,
+    Bcd16,
+}
+
+impl ValueType {
+    pub const ALL: [ValueType; 4] = [ValueType::U8, ValueType::U16, ValueType::Bcd8, ValueType::Bcd16];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ValueType::ALL.into_iter().find(|value_type| value_type.name().eq_ignore_ascii_case(name))
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueType::U8 => "u8",
+            ValueType::U16 => "u16",
+            ValueType::Bcd8 => "bcd8",
+            ValueType::Bcd16 => "bcd16",
+        }
+    }
+    pub fn size(&self) -> u16 {
+        match self {
+            ValueType::U8 | ValueType::Bcd8 => 1,
+            ValueType::U16 | ValueType::Bcd16 => 2,
+        }
+    }
+    // Gir None for BCD med et siffer over 9
+    pub fn read(&self, game_boy: &GameBoy, address: u16) -> Option<u32> {
+        let raw = (0..self.size()).rev().fold(0, |value, i| value << 8 | game_boy.peek_memory(address + i) as u32);
+        match self {
+            ValueType::U8 | ValueType::U16 => Some(raw),
+            ValueType::Bcd8 | ValueType::Bcd16 => (0..2 * self.size()).rev().try_fold(0, |value, i| {
+                let digit = raw >> (4 * i) & 0x0f;
+                (digit <= 9).then_some(value * 10 + digit)
+            }),
+        }
+    }
+    // Bytene verdien lagres som, i stigende adresse
+    pub fn encode(&self, value: u32) -> Option<Vec<u8>> {
+        let raw = match self {
+            ValueType::U8 | ValueType::U16 => value,
+            ValueType::Bcd8 | ValueType::Bcd16 => {
+                let digits = 2 * self.size() as u32;
+                if value >= 10u32.pow(digits) { return None }
+                (0..digits).fold(0, |raw, i| raw | (value / 10u32.pow(i) % 10) << (4 * i))
+            }
+        };
+        if raw >> (8 * self.size()) != 0 { return None }
+        Some((0..self.size()).map(|i| (raw >> (8 * i)) as u8).collect())
+    }
+    // GameShark-koder som holder verdien fast på adressen, én per byte
+    pub fn cheats(&self, address: u16, value: u32) -> Result<Vec<Cheat>, &'static str> {
+        let bytes = self.encode(value).ok_or("Verdien passer ikke i typen")?;
+        bytes.into_iter().enumerate().map(|(i, byte)| {
+            let address = address.wrapping_add(i as u16);
+            Cheat::parse(&format!("01{:02X}{:02X}{:02X}", byte, address & 0xff, address >> 8), &format!("RAM-søk {:#06x}", address))
+        }).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchFilter {
+    Unchanged,
+    Changed,
+    Increased,
+    Decreased,
+    Equal(u32),
+}
+
+impl SearchFilter {
+    // `eq`, `ne`, `inc`, `dec` eller et tall, desimalt eller med 0x foran
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "eq" | "unchanged" => Some(SearchFilter::Unchanged),
+            "ne" | "changed" => Some(SearchFilter::Changed),
+            "inc" | "increased" => Some(SearchFilter::Increased),
+            "dec" | "decreased" => Some(SearchFilter::Decreased),
+            value => match value.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => value.parse().ok(),
+            }.map(SearchFilter::Equal),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchResult {
+    pub address: u16,
+    pub value: u32,
+    // Verdien ved forrige filtrering
+    pub previous: u32,
+}
+
+// Starter med alle adressene i RAM, og hver filtrering beholder adressene der verdien passer.
+// Brukes til å finne adressen til f.eks. antall liv, så det kan lages en GameShark-kode for den
+pub struct RamSearch {
+    value_type: ValueType,
+    candidates: Vec<(u16, u32)>,
+}
+
+impl RamSearch {
+    pub fn new(game_boy: &GameBoy, value_type: ValueType) -> Self {
+        let mut regions = vec![WORK_RAM, HIGH_RAM];
+        if game_boy.has_cartridge_ram() {
+            regions.push(CARTRIDGE_RAM);
+        }
+        let candidates = regions.into_iter()
+            .flat_map(|(start, end)| start..=end + 1 - value_type.size())
+            .filter_map(|address| value_type.read(game_boy, address).map(|value| (address, value)))
+            .collect();
+        Self { value_type, candidates }
+    }
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+    pub fn filter(&mut self, game_boy: &GameBoy, filter: SearchFilter) {
+        let value_type = self.value_type;
+        self.candidates.retain_mut(|(address, previous)| {
+            let Some(value) = value_type.read(game_boy, *address) else { return false };
+            let keep = match filter {
+                SearchFilter::Unchanged => value == *previous,
+                SearchFilter::Changed => value != *previous,
+                SearchFilter::Increased => value > *previous,
+                SearchFilter::Decreased => value < *previous,
+                SearchFilter::Equal(wanted) => value == wanted,
+            };
+            *previous = value;
+            keep
+        });
+    }
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+    // De første treffene med verdien slik den er nå
+    pub fn results(&self, game_boy: &GameBoy, limit: usize) -> Vec<SearchResult> {
+        self.candidates.iter().take(limit).map(|&(address, previous)| SearchResult {
+            address,
+            value: self.value_type.read(game_boy, address).unwrap_or(previous),
+            previous,
+        }).collect()
+    }
+}
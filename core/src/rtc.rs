@@ -0,0 +1,105 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+pub const RTC_STATE_SIZE: usize = 16;
+
+// Kilden til sanntidsklokken i MBC3. Gir sekunder siden 1970
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+    }
+}
+
+// Står stille på samme tid, slik at kjøringer kan gjentas nøyaktig
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
+    }
+}
+
+pub struct Rtc {
+    clock: Box<dyn Clock>,
+    // Klokketiden da telleren stod på 0
+    base: u64,
+    // Tellerverdien mens klokken er stoppet (halt-biten i DH)
+    halted: Option<u64>,
+    day_carry: bool,
+    latched: [u8; 5],
+    latch_armed: bool,
+}
+
+impl Rtc {
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        let base = clock.now();
+        Self {
+            clock,
+            base,
+            halted: None,
+            day_carry: false,
+            latched: [0; 5],
+            latch_armed: false,
+        }
+    }
+    fn counter(&self) -> u64 {
+        self.halted.unwrap_or_else(|| self.clock.now().saturating_sub(self.base))
+    }
+    // Sekunder, minutter, timer, dag (lav) og DH (dag bit 8, halt og carry)
+    fn registers(&self) -> [u8; 5] {
+        let counter = self.counter();
+        let days = counter / SECONDS_PER_DAY;
+        let day_high = ((days >> 8) & 0x01) as u8
+            | if self.halted.is_some() { 1 << 6 } else { 0 }
+            | if self.day_carry || days > 0x1ff { 1 << 7 } else { 0 };
+        [(counter % 60) as u8, (counter / 60 % 60) as u8, (counter / 3600 % 24) as u8, days as u8, day_high]
+    }
+    fn set_registers(&mut self, [seconds, minutes, hours, day_low, day_high]: [u8; 5]) {
+        let days = day_low as u64 | ((day_high as u64 & 0x01) << 8);
+        let counter = seconds as u64 + minutes as u64 * 60 + hours as u64 * 3600 + days * SECONDS_PER_DAY;
+        self.day_carry = day_high & (1 << 7) != 0;
+        match day_high & (1 << 6) {
+            0 => {
+                self.halted = None;
+                self.base = self.clock.now().saturating_sub(counter);
+            }
+            _ => self.halted = Some(counter),
+        }
+    }
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[(register - 0x08) as usize]
+    }
+    pub fn write(&mut self, register: u8, value: u8) {
+        let mut registers = self.registers();
+        registers[(register - 0x08) as usize] = value;
+        self.set_registers(registers);
+    }
+    // Skriving av 0 og så 1 kopierer klokken til registrene spillet leser
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.latched = self.registers();
+        }
+        self.latch_armed = value == 0x00;
+    }
+    // Registrene og klokketiden lagres etter RAM-en, slik at klokken går videre mens emulatoren er av
+    pub fn save_state(&self) -> [u8; RTC_STATE_SIZE] {
+        let mut state = [0; RTC_STATE_SIZE];
+        state[..5].copy_from_slice(&self.registers());
+        state[8..].copy_from_slice(&self.clock.now().to_le_bytes());
+        state
+    }
+    pub fn load_state(&mut self, state: &[u8]) {
+        let registers: [u8; 5] = state[..5].try_into().unwrap();
+        let saved_at = u64::from_le_bytes(state[8..RTC_STATE_SIZE].try_into().unwrap());
+        self.set_registers(registers);
+        if saved_at != 0 && self.halted.is_none() {
+            self.base = self.base.saturating_sub(self.clock.now().saturating_sub(saved_at));
+        }
+    }
+}
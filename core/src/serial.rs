@@ -0,0 +1,79 @@
+// En bit overføres hver 512. T-syklus med intern klokke (8192 Hz)
+const TRANSFER_CYCLES: u32 = 8 * 512;
+
+// Det som er koblet til link-porten. Får byten som sendes og gir byten som mottas
+pub trait SerialDevice {
+    fn exchange(&mut self, byte: u8) -> u8;
+}
+
+// Skriver alt som sendes til stdout, slik testrom-ene til Blargg forventer
+pub struct StdoutSerial;
+
+impl SerialDevice for StdoutSerial {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        if let Ok(s) = String::from_utf8(vec![byte]) { print!("{}", s); }
+        0xff
+    }
+}
+
+pub struct Serial {
+    data: u8,
+    control: u8,
+    remaining_cycles: u32,
+    device: Option<Box<dyn SerialDevice>>,
+    pub instant: bool,
+    pub interrupt: u8,
+}
+
+impl Serial {
+    pub fn new(device: Option<Box<dyn SerialDevice>>) -> Self {
+        Self {
+            data: 0,
+            control: 0,
+            remaining_cycles: 0,
+            device,
+            instant: false,
+            interrupt: 0,
+        }
+    }
+    pub fn read_byte(&self, address: u8) -> u8 {
+        match address {
+            0x01 => self.data,
+            0x02 => self.control | 0x7e,
+            _ => unreachable!()
+        }
+    }
+    pub fn write_byte(&mut self, address: u8, value: u8) {
+        match address {
+            0x01 => self.data = value,
+            0x02 => {
+                self.control = value;
+                // Med ekstern klokke venter vi på en motpart som aldri kommer
+                if value & 0x81 == 0x81 {
+                    self.remaining_cycles = TRANSFER_CYCLES;
+                    if self.instant {
+                        self.complete_transfer();
+                    }
+                }
+            }
+            _ => unreachable!()
+        }
+    }
+    pub fn cycle(&mut self, t_cycles: u32) {
+        if self.remaining_cycles == 0 { return }
+
+        self.remaining_cycles = self.remaining_cycles.saturating_sub(t_cycles);
+        if self.remaining_cycles == 0 {
+            self.complete_transfer();
+        }
+    }
+    fn complete_transfer(&mut self) {
+        self.remaining_cycles = 0;
+        self.data = match self.device {
+            Some(ref mut device) => device.exchange(self.data),
+            None => 0xff,
+        };
+        self.control &= 0x7f;
+        self.interrupt |= 1 << 3;
+    }
+}
@@ -0,0 +1,400 @@
+use log::debug;
+use crate::frame_buffer::CGB_COLOR;
+
+// Super Game Boy: spillet sender kommandoer i pakker på 16 byte ved å pulse P14/P15 i joypad-registeret.
+// SGB-en fargelegger skjermen med fire paletter og tegner en ramme på 256x224 rundt den.
+pub const SGB_SCREEN_WIDTH: usize = 256;
+pub const SGB_SCREEN_HEIGHT: usize = 224;
+const GAME_SCREEN_WIDTH: usize = 160;
+const GAME_SCREEN_HEIGHT: usize = 144;
+const GAME_SCREEN_X: usize = 48;
+const GAME_SCREEN_Y: usize = 40;
+const ATTRIBUTE_COLUMNS: usize = 20;
+const ATTRIBUTE_ROWS: usize = 18;
+const PACKET_SIZE: usize = 16;
+const TRANSFER_SIZE: usize = 0x1000;
+const ATTRIBUTE_FILE_SIZE: usize = 90;
+const ATTRIBUTE_FILES: usize = 45;
+const SYSTEM_PALETTES: usize = 512;
+const BORDER_TILES: usize = 256;
+const BORDER_MAP_SIZE: usize = 32 * 32;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MaskMode {
+    Cancel,
+    Freeze,
+    Black,
+    Color0,
+}
+
+#[derive(Clone, Copy)]
+enum Transfer {
+    SystemPalettes,
+    BorderTiles(usize),
+    BorderMap,
+    AttributeFiles,
+}
+
+pub struct Sgb {
+    previous_lines: u8,
+    receiving: bool,
+    packet: [u8; PACKET_SIZE],
+    bit_index: usize,
+    command: Vec<u8>,
+    remaining_packets: usize,
+    players: u8,
+    current_player: u8,
+    palettes: [[u16; 4]; 4],
+    system_palettes: Vec<[u16; 4]>,
+    attributes: [u8; ATTRIBUTE_COLUMNS * ATTRIBUTE_ROWS],
+    attribute_files: Vec<[u8; ATTRIBUTE_FILE_SIZE]>,
+    border_tiles: Vec<u8>,
+    border_map: Vec<u16>,
+    border_palettes: [[u16; 16]; 4],
+    mask: MaskMode,
+    pending_transfer: Option<Transfer>,
+    frame_buffer: Vec<u16>,
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Self {
+            previous_lines: 0x30,
+            receiving: false,
+            packet: [0; PACKET_SIZE],
+            bit_index: 0,
+            command: Vec::new(),
+            remaining_packets: 0,
+            players: 1,
+            current_player: 0,
+            palettes: [[0x7fff, 0x56b5, 0x294a, 0x0000]; 4],
+            system_palettes: vec![[0; 4]; SYSTEM_PALETTES],
+            attributes: [0; ATTRIBUTE_COLUMNS * ATTRIBUTE_ROWS],
+            attribute_files: vec![[0; ATTRIBUTE_FILE_SIZE]; ATTRIBUTE_FILES],
+            border_tiles: vec![0; BORDER_TILES * 32],
+            border_map: vec![0; BORDER_MAP_SIZE],
+            border_palettes: [[0; 16]; 4],
+            mask: MaskMode::Cancel,
+            pending_transfer: None,
+            frame_buffer: vec![0; SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT],
+        }
+    }
+    pub fn write_joypad(&mut self, value: u8) {
+        let lines = value & 0x30;
+        match lines {
+            // Reset-puls: starter en ny pakke
+            0x00 => {
+                self.receiving = true;
+                self.packet = [0; PACKET_SIZE];
+                self.bit_index = 0;
+            }
+            0x10 | 0x20 if self.receiving && self.previous_lines == 0x30 => {
+                // P15 lav (0x10) er en 1-bit, P14 lav (0x20) er en 0-bit
+                let bit = (lines == 0x10) as u8;
+                if self.bit_index < PACKET_SIZE * 8 {
+                    self.packet[self.bit_index / 8] |= bit << (self.bit_index % 8);
+                    self.bit_index += 1;
+                } else {
+                    // Stoppbit etter 128 bit
+                    self.receiving = false;
+                    self.receive_packet();
+                }
+            }
+            _ => {}
+        }
+        // Neste kontroller velges når P15 går lav
+        if !self.receiving && self.previous_lines & 0x20 != 0 && lines & 0x20 == 0 {
+            self.current_player = (self.current_player + 1) % self.players;
+        }
+        self.previous_lines = lines;
+    }
+    pub fn current_player(&self) -> u8 {
+        self.current_player
+    }
+    pub fn multiplayer(&self) -> bool {
+        self.players > 1
+    }
+    fn receive_packet(&mut self) {
+        if self.remaining_packets == 0 {
+            self.command.clear();
+            self.remaining_packets = (self.packet[0] & 0x07).max(1) as usize;
+        }
+        self.command.extend_from_slice(&self.packet);
+        self.remaining_packets -= 1;
+        if self.remaining_packets == 0 {
+            let command = std::mem::take(&mut self.command);
+            self.execute(&command);
+        }
+    }
+    fn execute(&mut self, command: &[u8]) {
+        match command[0] >> 3 {
+            0x00 => self.set_palette_pair(command, 0, 1),
+            0x01 => self.set_palette_pair(command, 2, 3),
+            0x02 => self.set_palette_pair(command, 0, 3),
+            0x03 => self.set_palette_pair(command, 1, 2),
+            0x04 => self.attribute_blocks(command),
+            0x05 => self.attribute_lines(command),
+            0x06 => self.attribute_division(command),
+            0x07 => self.attribute_characters(command),
+            0x0a => self.set_system_palettes(command),
+            0x0b => self.pending_transfer = Some(Transfer::SystemPalettes),
+            0x11 => {
+                self.players = match command[1] & 0x03 {
+                    0 => 1,
+                    1 => 2,
+                    _ => 4,
+                };
+                self.current_player = 0;
+            }
+            0x13 => self.pending_transfer = Some(Transfer::BorderTiles((command[1] & 0x01) as usize)),
+            0x14 => self.pending_transfer = Some(Transfer::BorderMap),
+            0x15 => self.pending_transfer = Some(Transfer::AttributeFiles),
+            0x16 => {
+                self.apply_attribute_file(command[1] & 0x3f);
+                if command[1] & 0x40 != 0 {
+                    self.mask = MaskMode::Cancel;
+                }
+            }
+            0x17 => self.mask = match command[1] & 0x03 {
+                0 => MaskMode::Cancel,
+                1 => MaskMode::Freeze,
+                2 => MaskMode::Black,
+                _ => MaskMode::Color0,
+            },
+            other => debug!("SGB-kommando {:#04x} er ikke støttet", other),
+        }
+    }
+    fn set_palette_pair(&mut self, command: &[u8], first: usize, second: usize) {
+        let color = |index: usize| u16::from_le_bytes([command[1 + 2 * index], command[2 + 2 * index]]) & 0x7fff;
+
+        // Farge 0 er felles for alle palettene
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color(0);
+        }
+        for i in 1..4 {
+            self.palettes[first][i] = color(i);
+            self.palettes[second][i] = color(i + 3);
+        }
+    }
+    fn attribute_blocks(&mut self, command: &[u8]) {
+        let data_sets = (command[1] & 0x1f) as usize;
+        for data_set in command[2..].chunks_exact(6).take(data_sets) {
+            let control = data_set[0] & 0x07;
+            let inside = data_set[1] & 0x03;
+            let border = (data_set[1] >> 2) & 0x03;
+            let outside = (data_set[1] >> 4) & 0x03;
+            // Er bare innsiden eller utsiden valgt, får kanten samme palett
+            let border = match control {
+                0x01 => Some(inside),
+                0x04 => Some(outside),
+                _ if control & 0x02 != 0 => Some(border),
+                _ => None,
+            };
+            let (x1, y1, x2, y2) = (data_set[2] as usize, data_set[3] as usize, data_set[4] as usize, data_set[5] as usize);
+
+            for y in 0..ATTRIBUTE_ROWS {
+                for x in 0..ATTRIBUTE_COLUMNS {
+                    let palette = if x > x1 && x < x2 && y > y1 && y < y2 {
+                        (control & 0x01 != 0).then_some(inside)
+                    } else if x < x1 || x > x2 || y < y1 || y > y2 {
+                        (control & 0x04 != 0).then_some(outside)
+                    } else {
+                        border
+                    };
+                    if let Some(palette) = palette {
+                        self.attributes[y * ATTRIBUTE_COLUMNS + x] = palette;
+                    }
+                }
+            }
+        }
+    }
+    fn attribute_lines(&mut self, command: &[u8]) {
+        let data_sets = command[1] as usize;
+        for &data_set in command[2..].iter().take(data_sets) {
+            let line = (data_set & 0x1f) as usize;
+            let palette = (data_set >> 5) & 0x03;
+            match data_set & 0x80 {
+                // Horisontal linje
+                0x80 if line < ATTRIBUTE_ROWS => self.attributes[line * ATTRIBUTE_COLUMNS..(line + 1) * ATTRIBUTE_COLUMNS].fill(palette),
+                0x00 if line < ATTRIBUTE_COLUMNS => (0..ATTRIBUTE_ROWS).for_each(|y| self.attributes[y * ATTRIBUTE_COLUMNS + line] = palette),
+                _ => {}
+            }
+        }
+    }
+    fn attribute_division(&mut self, command: &[u8]) {
+        let after = command[1] & 0x03;
+        let before = (command[1] >> 2) & 0x03;
+        let on_line = (command[1] >> 4) & 0x03;
+        let horizontal = command[1] & 0x40 != 0;
+        let division = command[2] as usize;
+
+        for y in 0..ATTRIBUTE_ROWS {
+            for x in 0..ATTRIBUTE_COLUMNS {
+                let position = if horizontal { y } else { x };
+                self.attributes[y * ATTRIBUTE_COLUMNS + x] = match position.cmp(&division) {
+                    std::cmp::Ordering::Less => before,
+                    std::cmp::Ordering::Equal => on_line,
+                    std::cmp::Ordering::Greater => after,
+                };
+            }
+        }
+    }
+    fn attribute_characters(&mut self, command: &[u8]) {
+        let mut x = command[1] as usize % ATTRIBUTE_COLUMNS;
+        let mut y = command[2] as usize % ATTRIBUTE_ROWS;
+        let data_sets = u16::from_le_bytes([command[3], command[4]]) as usize;
+        let vertical = command[5] & 0x01 != 0;
+
+        for index in 0..data_sets.min(ATTRIBUTE_COLUMNS * ATTRIBUTE_ROWS) {
+            let Some(byte) = command.get(6 + index / 4) else { break };
+            self.attributes[y * ATTRIBUTE_COLUMNS + x] = (byte >> (6 - 2 * (index % 4))) & 0x03;
+            match vertical {
+                false => {
+                    x += 1;
+                    if x == ATTRIBUTE_COLUMNS { x = 0; y = (y + 1) % ATTRIBUTE_ROWS }
+                }
+                true => {
+                    y += 1;
+                    if y == ATTRIBUTE_ROWS { y = 0; x = (x + 1) % ATTRIBUTE_COLUMNS }
+                }
+            }
+        }
+    }
+    fn set_system_palettes(&mut self, command: &[u8]) {
+        for (i, palette) in self.palettes.iter_mut().enumerate() {
+            let number = u16::from_le_bytes([command[1 + 2 * i], command[2 + 2 * i]]) as usize % SYSTEM_PALETTES;
+            *palette = self.system_palettes[number];
+        }
+        // Farge 0 fra den første paletten brukes av alle
+        let color_0 = self.palettes[0][0];
+        self.palettes.iter_mut().for_each(|palette| palette[0] = color_0);
+
+        if command[9] & 0x80 != 0 {
+            self.apply_attribute_file(command[9] & 0x3f);
+        }
+        if command[9] & 0x40 != 0 {
+            self.mask = MaskMode::Cancel;
+        }
+    }
+    fn apply_attribute_file(&mut self, file: u8) {
+        let Some(file) = self.attribute_files.get(file as usize) else { return };
+        for (index, attribute) in self.attributes.iter_mut().enumerate() {
+            *attribute = (file[index / 4] >> (6 - 2 * (index % 4))) & 0x03;
+        }
+    }
+    // Kalles ved hver VBlank med den ferdige DMG-framebufferen
+    pub fn vertical_blank(&mut self, game_screen: &[u16]) {
+        if let Some(transfer) = self.pending_transfer.take() {
+            let data = Self::vram_transfer_data(game_screen);
+            self.complete_transfer(transfer, &data);
+        }
+        self.render(game_screen);
+    }
+    // SGB-en leser 4 KB ved å tolke de første 256 tilene på skjermen som 2bpp-data igjen
+    fn vram_transfer_data(game_screen: &[u16]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(TRANSFER_SIZE);
+        for tile in 0..TRANSFER_SIZE / 16 {
+            let tile_x = (tile % ATTRIBUTE_COLUMNS) * 8;
+            let tile_y = (tile / ATTRIBUTE_COLUMNS) * 8;
+            for row in 0..8 {
+                let (mut low, mut high) = (0, 0);
+                for x in 0..8 {
+                    let shade = game_screen[(tile_y + row) * GAME_SCREEN_WIDTH + tile_x + x] & 0x03;
+                    low |= ((shade & 0x01) as u8) << (7 - x);
+                    high |= ((shade >> 1) as u8) << (7 - x);
+                }
+                data.push(low);
+                data.push(high);
+            }
+        }
+        data
+    }
+    fn complete_transfer(&mut self, transfer: Transfer, data: &[u8]) {
+        let color = |index: usize| u16::from_le_bytes([data[2 * index], data[2 * index + 1]]) & 0x7fff;
+        match transfer {
+            Transfer::SystemPalettes => {
+                for (number, palette) in self.system_palettes.iter_mut().enumerate() {
+                    *palette = std::array::from_fn(|i| color(4 * number + i));
+                }
+            }
+            Transfer::BorderTiles(bank) => {
+                let start = bank * TRANSFER_SIZE;
+                self.border_tiles[start..start + TRANSFER_SIZE].copy_from_slice(data);
+            }
+            Transfer::BorderMap => {
+                for (i, entry) in self.border_map.iter_mut().enumerate() {
+                    *entry = u16::from_le_bytes([data[2 * i], data[2 * i + 1]]);
+                }
+                // Palett 4-7 ligger etter tile-kartet
+                for (number, palette) in self.border_palettes.iter_mut().enumerate() {
+                    *palette = std::array::from_fn(|i| color(BORDER_MAP_SIZE + 16 * number + i));
+                }
+            }
+            Transfer::AttributeFiles => {
+                for (number, file) in self.attribute_files.iter_mut().enumerate() {
+                    let start = number * ATTRIBUTE_FILE_SIZE;
+                    file.copy_from_slice(&data[start..start + ATTRIBUTE_FILE_SIZE]);
+                }
+            }
+        }
+    }
+    fn render(&mut self, game_screen: &[u16]) {
+        let backdrop = CGB_COLOR | self.palettes[0][0];
+
+        for tile_y in 0..SGB_SCREEN_HEIGHT / 8 {
+            for tile_x in 0..SGB_SCREEN_WIDTH / 8 {
+                // Spillskjermen dekker midten av rammen
+                if (6..26).contains(&tile_x) && (5..23).contains(&tile_y) { continue }
+                self.render_border_tile(tile_x, tile_y, backdrop);
+            }
+        }
+
+        if self.mask == MaskMode::Freeze { return }
+        for y in 0..GAME_SCREEN_HEIGHT {
+            for x in 0..GAME_SCREEN_WIDTH {
+                let color = match self.mask {
+                    MaskMode::Black => CGB_COLOR,
+                    MaskMode::Color0 => backdrop,
+                    _ => {
+                        let palette = self.attributes[(y / 8) * ATTRIBUTE_COLUMNS + x / 8] as usize;
+                        let shade = (game_screen[y * GAME_SCREEN_WIDTH + x] & 0x03) as usize;
+                        CGB_COLOR | self.palettes[palette][shade]
+                    }
+                };
+                self.frame_buffer[(GAME_SCREEN_Y + y) * SGB_SCREEN_WIDTH + GAME_SCREEN_X + x] = color;
+            }
+        }
+    }
+    fn render_border_tile(&mut self, tile_x: usize, tile_y: usize, backdrop: u16) {
+        let entry = self.border_map[tile_y * 32 + tile_x];
+        let tile = &self.border_tiles[(entry & 0xff) as usize * 32..][..32];
+        let palette = &self.border_palettes[((entry >> 10) & 0x03) as usize];
+        let x_flip = entry & (1 << 14) != 0;
+        let y_flip = entry & (1 << 15) != 0;
+
+        for row in 0..8 {
+            let tile_row = if y_flip { 7 - row } else { row };
+            // SNES-tiles har 4 bitplan: plan 0 og 1 i de første 16 bytene, plan 2 og 3 i de neste
+            let planes = [tile[2 * tile_row], tile[2 * tile_row + 1], tile[16 + 2 * tile_row], tile[17 + 2 * tile_row]];
+            for column in 0..8 {
+                let bit = if x_flip { column } else { 7 - column };
+                let color_index = planes.iter().enumerate()
+                    .fold(0, |index, (plane, data)| index | (((data >> bit) & 1) as usize) << plane);
+                let color = match color_index {
+                    0 => backdrop,
+                    _ => CGB_COLOR | palette[color_index],
+                };
+                self.frame_buffer[(tile_y * 8 + row) * SGB_SCREEN_WIDTH + tile_x * 8 + column] = color;
+            }
+        }
+    }
+    pub fn read_frame_buffer(&self) -> &[u16] {
+        &self.frame_buffer
+    }
+}
@@ -23,6 +23,9 @@ impl Timer {
             interrupt: 0,
         }
     }
+    pub fn set_divider(&mut self, divider: u8) {
+        self.divider = divider;
+    }
     pub fn cycle(&mut self, m_cycles: u32) {
         self.internal_divider += 4 * m_cycles;
         while self.internal_divider > 256 {
@@ -0,0 +1,108 @@
+// Felles hjelpefunksjoner for testene som kjører ekte ROM-er
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::env;
+use std::any::Any;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use gameboy_core::serial::SerialDevice;
+
+// Samler opp alt ROM-en sender over link-porten
+pub struct CaptureSerial {
+    pub output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SerialDevice for CaptureSerial {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        self.output.borrow_mut().push(byte);
+        0xff
+    }
+}
+
+// Katalogen fra miljøvariabelen, eller en standardkatalog relativt til workspace-roten
+pub fn directory(variable: &str, default: &str) -> PathBuf {
+    match env::var_os(variable) {
+        Some(directory) => PathBuf::from(directory),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(default),
+    }
+}
+
+// Som directory, men feiler hvis katalogen mangler
+pub fn data_directory(variable: &str, default: &str) -> PathBuf {
+    let directory = directory(variable, default);
+    assert!(directory.is_dir(), "Fant ikke {} (sett {} for å bruke en annen katalog)", directory.display(), variable);
+    directory
+}
+
+// Alle .gb- og .gbc-filer under katalogen, sortert. Kataloger i exclude hoppes over
+pub fn find_roms(directory: &Path, exclude: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roms = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if !exclude.iter().any(|excluded| same_path(excluded, &path)) {
+                    directories.push(path);
+                }
+            } else if matches!(path.extension().and_then(|extension| extension.to_str()), Some("gb" | "gbc")) {
+                roms.push(path);
+            }
+        }
+    }
+    roms.sort();
+    roms
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+pub fn env_number(variable: &str, default: u32) -> u32 {
+    env::var(variable).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+// Krever at alle ROM-ene består når variabelen er satt, ellers rapporteres resultatet bare
+pub fn strict() -> bool {
+    env::var_os("GAMEBOY_TEST_ROMS_STRICT").is_some()
+}
+
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub details: String,
+}
+
+// Skriver en tabell over resultatene og gir antall som feilet
+pub fn print_table(suite: &str, results: &[TestResult]) -> usize {
+    let width = results.iter().map(|result| result.name.len()).max().unwrap_or(0).max(4);
+    println!();
+    println!("{:<width$}  Resultat  Detaljer", "Test");
+    println!("{}", "-".repeat(width + 20));
+    for result in results {
+        let status = if result.passed { "OK" } else { "FEIL" };
+        println!("{:<width$}  {:<8}  {}", result.name, status, result.details);
+    }
+    let failed = results.iter().filter(|result| !result.passed).count();
+    println!("{}: {} av {} bestod", suite, results.len() - failed, results.len());
+    failed
+}
+
+// Første linje av meldingen fra en panikk fanget med catch_unwind
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    format!("panikk: {}", message.lines().next().unwrap_or(""))
+}
+
+pub fn rom_name(directory: &Path, rom: &Path) -> String {
+    rom.strip_prefix(directory).unwrap_or(rom).display().to_string()
+}
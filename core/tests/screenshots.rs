@@ -0,0 +1,165 @@
+// Kjører ROM-er et fast antall frames og sammenligner skjermbildet med et referansebilde.
+// Testene står i screenshots.txt. Når et bilde ikke stemmer lagres et diff-bilde med
+// forventet, faktisk og forskjellene side om side. Med GAMEBOY_UPDATE_SCREENSHOTS
+// skrives referansebildene på nytt i stedet.
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use common::TestResult;
+use gameboy_core::game_boy::GameBoy;
+use gameboy_core::model::BootMode;
+
+struct Screenshot {
+    rom: PathBuf,
+    frames: u32,
+    reference: PathBuf,
+}
+
+struct Image {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+fn parse_manifest(text: &str, directory: &Path) -> Vec<Screenshot> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts[..] {
+                [rom, frames, reference] => Screenshot {
+                    rom: directory.join(rom),
+                    frames: frames.parse().unwrap_or_else(|_| panic!("Ugyldig antall frames i screenshots.txt: {}", line)),
+                    reference: directory.join(reference),
+                },
+                _ => panic!("Ugyldig linje i screenshots.txt: {}", line),
+            }
+        })
+        .collect()
+}
+
+fn read_png(path: &Path) -> Result<Image, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|error| error.to_string())?;
+    let pixels = &buffer[..info.buffer_size()];
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels.chunks(3).flat_map(|p| [p[0], p[1], p[2], 0xff]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&p| [p, p, p, 0xff]).collect(),
+        png::ColorType::Indexed => return Err("indekserte farger skulle vært utvidet".to_string()),
+    };
+    Ok(Image { width: info.width, height: info.height, rgba })
+}
+
+fn write_png(path: &Path, image: &Image) -> Result<(), String> {
+    let file = File::create(path).map_err(|error| error.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().and_then(|mut writer| writer.write_image_data(&image.rgba)).map_err(|error| error.to_string())
+}
+
+fn render(screenshot: &Screenshot) -> Result<Image, String> {
+    let data = fs::read(&screenshot.rom).map_err(|error| error.to_string())?;
+    let mut game_boy = GameBoy::builder(data).boot_mode(BootMode::Skip).build()?;
+    game_boy.run_frames(screenshot.frames);
+    let (width, height) = game_boy.screen_size();
+    let mut rgba = vec![0; (width * height * 4) as usize];
+    game_boy.write_frame(&mut rgba);
+    Ok(Image { width, height, rgba })
+}
+
+// Forventet | faktisk | forskjeller. Pikslene som er like vises dempet, de som er ulike i rødt
+fn diff_image(expected: &Image, actual: &Image) -> (Image, usize) {
+    let width = expected.width.max(actual.width);
+    let height = expected.height.max(actual.height);
+    let mut rgba = vec![0xff; (width * 3 * height * 4) as usize];
+    let pixel = |image: &Image, x: u32, y: u32| -> Option<[u8; 4]> {
+        if x >= image.width || y >= image.height { return None }
+        let i = ((y * image.width + x) * 4) as usize;
+        Some([image.rgba[i], image.rgba[i + 1], image.rgba[i + 2], image.rgba[i + 3]])
+    };
+    let mut differences = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let a = pixel(expected, x, y);
+            let b = pixel(actual, x, y);
+            let diff = match (a, b) {
+                (Some(a), Some(b)) if a == b => {
+                    let gray = (a[0] as u32 + a[1] as u32 + a[2] as u32) / 3 / 4 + 0xc0;
+                    [gray as u8, gray as u8, gray as u8, 0xff]
+                }
+                _ => {
+                    differences += 1;
+                    [0xff, 0x00, 0x00, 0xff]
+                }
+            };
+            for (column, color) in [a, b, Some(diff)].into_iter().enumerate() {
+                let i = ((y * width * 3 + column as u32 * width + x) * 4) as usize;
+                rgba[i..i + 4].copy_from_slice(&color.unwrap_or([0xff, 0x00, 0xff, 0xff]));
+            }
+        }
+    }
+    (Image { width: width * 3, height, rgba }, differences)
+}
+
+fn compare(screenshot: &Screenshot, update: bool, diff_directory: &Path) -> (bool, String) {
+    let actual = match render(screenshot) {
+        Ok(actual) => actual,
+        Err(error) => return (false, error),
+    };
+    if update {
+        return match write_png(&screenshot.reference, &actual) {
+            Ok(()) => (true, "oppdatert".to_string()),
+            Err(error) => (false, format!("kunne ikke skrive referansen: {}", error)),
+        };
+    }
+    let expected = match read_png(&screenshot.reference) {
+        Ok(expected) => expected,
+        Err(error) => return (false, format!("kunne ikke lese referansen: {}", error)),
+    };
+    let (diff, differences) = diff_image(&expected, &actual);
+    if differences == 0 {
+        return (true, "likt".to_string());
+    }
+    let name = screenshot.rom.file_stem().unwrap_or_default().to_string_lossy();
+    let path = diff_directory.join(format!("{}.png", name));
+    match write_png(&path, &diff) {
+        Ok(()) => (false, format!("{} piksler ulike, se {}", differences, path.display())),
+        Err(error) => (false, format!("{} piksler ulike (kunne ikke skrive diff: {})", differences, error)),
+    }
+}
+
+#[test]
+#[ignore = "trenger ROM-ene i screenshots.txt under roms_test eller GAMEBOY_SCREENSHOT_ROMS"]
+fn screenshots() {
+    let directory = common::data_directory("GAMEBOY_SCREENSHOT_ROMS", "roms_test");
+    let manifest = include_str!("screenshots.txt");
+    let update = env::var_os("GAMEBOY_UPDATE_SCREENSHOTS").is_some();
+    let diff_directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("screenshot-diff");
+    fs::create_dir_all(&diff_directory).unwrap();
+
+    let mut results = Vec::new();
+    for screenshot in parse_manifest(manifest, &directory) {
+        let name = common::rom_name(&directory, &screenshot.rom);
+        if !screenshot.rom.is_file() {
+            println!("Hopper over {}: ROM-en mangler", name);
+            continue;
+        }
+        let (passed, details) = compare(&screenshot, update, &diff_directory);
+        results.push(TestResult { name, passed, details });
+    }
+    assert!(!results.is_empty(), "Fant ingen av ROM-ene i screenshots.txt under {}", directory.display());
+    let failed = common::print_table("Skjermbilder", &results);
+    assert_eq!(failed, 0, "{} skjermbilder stemte ikke", failed);
+}
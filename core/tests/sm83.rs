@@ -0,0 +1,169 @@
+// Kjører én instruksjon om gangen mot SingleStepTests sine SM83-tester
+// (https://github.com/SingleStepTests/sm83) og sammenligner registre, minne og lesingene og skrivingene i hver M-syklus.
+// Katalogen med JSON-filene settes med GAMEBOY_SM83_TESTS. Med GAMEBOY_TEST_ROMS_STRICT feiler testen ved avvik.
+mod common;
+
+use std::cell::RefCell;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use common::TestResult;
+use gameboy_core::bus::{Bus, FlatRam};
+use gameboy_core::cpu::{CpuState, CPU};
+use serde_json::Value;
+
+// FlatRam som husker hver lesing og skriving, for å sammenligne med "cycles" i testene
+struct LoggingRam {
+    ram: FlatRam,
+    accesses: RefCell<Vec<(u16, u8, char)>>,
+}
+
+impl Bus for LoggingRam {
+    fn read_byte(&self, address: u16) -> u8 {
+        let value = self.ram.read_byte(address);
+        self.accesses.borrow_mut().push((address, value, 'r'));
+        value
+    }
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.accesses.borrow_mut().push((address, value, 'w'));
+        self.ram.write_byte(address, value)
+    }
+    fn interrupt_enable(&self) -> u8 {
+        self.ram.interrupt_enable()
+    }
+    fn interrupt_flag(&self) -> u8 {
+        self.ram.interrupt_flag()
+    }
+    fn set_interrupt_flag(&mut self, value: u8) {
+        self.ram.set_interrupt_flag(value)
+    }
+    fn tick(&mut self, m_cycles: u32) -> u32 {
+        self.ram.tick(m_cycles)
+    }
+}
+
+fn number(state: &Value, name: &str) -> u16 {
+    state[name].as_u64().unwrap_or_else(|| panic!("Mangler {} i testen", name)) as u16
+}
+
+fn memory(state: &Value) -> Vec<(u16, u8)> {
+    state["ram"].as_array().map(|ram| ram.iter().map(|entry| (entry[0].as_u64().unwrap() as u16, entry[1].as_u64().unwrap() as u8)).collect()).unwrap_or_default()
+}
+
+fn cpu_state(state: &Value) -> CpuState {
+    CpuState {
+        a: number(state, "a") as u8,
+        f: number(state, "f") as u8,
+        b: number(state, "b") as u8,
+        c: number(state, "c") as u8,
+        d: number(state, "d") as u8,
+        e: number(state, "e") as u8,
+        h: number(state, "h") as u8,
+        l: number(state, "l") as u8,
+        sp: number(state, "sp"),
+        pc: number(state, "pc"),
+        ime: state["ime"].as_u64() == Some(1),
+        halted: false,
+    }
+}
+
+// Lesingene og skrivingene i testen. M-sykluser uten minnetilgang er null eller "---"
+fn bus_activity(test: &Value) -> Vec<(u16, u8, char)> {
+    test["cycles"].as_array().into_iter().flatten().filter_map(|cycle| {
+        let kind = cycle[2].as_str()?;
+        let kind = if kind.starts_with('r') { 'r' } else if kind.get(1..2) == Some("w") { 'w' } else { return None };
+        Some((cycle[0].as_u64()? as u16, cycle[1].as_u64()? as u8, kind))
+    }).collect()
+}
+
+// Gir en beskrivelse av første avvik, eller None hvis instruksjonen oppførte seg riktig
+fn run_test(cpu: &mut CPU<LoggingRam>, test: &Value) -> Option<String> {
+    let initial = &test["initial"];
+    let expected = &test["final"];
+    cpu.set_state(cpu_state(initial));
+    for (address, value) in memory(initial) {
+        cpu.bus.write_byte(address, value);
+    }
+    cpu.bus.accesses.borrow_mut().clear();
+
+    // Emulatoren panikker på opkoder den ikke støtter, og det skal bare gi en feilet test
+    let error = match panic::catch_unwind(AssertUnwindSafe(|| cpu.cycle())) {
+        Ok(m_cycles) => compare(cpu, test, m_cycles),
+        Err(payload) => Some(common::panic_message(payload)),
+    };
+
+    // Nullstiller minnet til neste test
+    for (address, _) in memory(initial).into_iter().chain(memory(expected)) {
+        cpu.bus.write_byte(address, 0);
+    }
+    error
+}
+
+fn compare(cpu: &CPU<LoggingRam>, test: &Value, m_cycles: u32) -> Option<String> {
+    let expected = &test["final"];
+    let accesses = cpu.bus.accesses.take();
+
+    // IME og HALT sammenlignes ikke, siden CPU-en oppdaterer dem ved neste instruksjon
+    let mut actual = cpu.state();
+    let mut wanted = cpu_state(expected);
+    (actual.ime, actual.halted, wanted.ime, wanted.halted) = (false, false, false, false);
+    let mut error = None;
+    if actual != wanted {
+        error = Some(format!("registre {:x?}, forventet {:x?}", actual, wanted));
+    }
+    for (address, value) in memory(expected) {
+        let actual = cpu.bus.read_byte(address);
+        if error.is_none() && actual != value {
+            error = Some(format!("{:#06x} = {:#04x}, forventet {:#04x}", address, actual, value));
+        }
+    }
+    let expected_cycles = test["cycles"].as_array().map_or(0, |cycles| cycles.len()) as u32;
+    if error.is_none() && m_cycles != expected_cycles {
+        error = Some(format!("{} M-sykluser, forventet {}", m_cycles, expected_cycles));
+    }
+    let expected_accesses = bus_activity(test);
+    if error.is_none() && accesses != expected_accesses {
+        error = Some(format!("minnetilganger {:x?}, forventet {:x?}", accesses, expected_accesses));
+    }
+    error
+}
+
+fn run_file(path: &Path) -> (bool, String) {
+    let tests: Value = match fs::read_to_string(path).map_err(|error| error.to_string()).and_then(|text| serde_json::from_str(&text).map_err(|error| error.to_string())) {
+        Ok(tests) => tests,
+        Err(error) => return (false, format!("kunne ikke lese: {}", error)),
+    };
+    let tests = tests.as_array().cloned().unwrap_or_default();
+    let mut cpu = CPU::with_bus(LoggingRam { ram: FlatRam::new(), accesses: RefCell::new(vec![]) });
+    let mut failed = 0;
+    let mut first_error = None;
+    for test in &tests {
+        if let Some(error) = run_test(&mut cpu, test) {
+            failed += 1;
+            first_error.get_or_insert_with(|| format!("{}: {}", test["name"].as_str().unwrap_or("?"), error));
+        }
+    }
+    match first_error {
+        None => (true, format!("{} tester", tests.len())),
+        Some(error) => (false, format!("{} av {} feilet, første: {}", failed, tests.len(), error)),
+    }
+}
+
+#[test]
+#[ignore = "trenger JSON-filene fra SingleStepTests/sm83 i roms_test/sm83/v1 eller GAMEBOY_SM83_TESTS"]
+fn sm83() {
+    let directory = common::data_directory("GAMEBOY_SM83_TESTS", "roms_test/sm83/v1");
+    let mut files: Vec<_> = fs::read_dir(&directory).unwrap().flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "Ingen JSON-filer i {}", directory.display());
+    let results: Vec<TestResult> = files.iter().map(|file| {
+        let (passed, details) = run_file(file);
+        TestResult { name: common::rom_name(&directory, file), passed, details }
+    }).collect();
+    let failed = common::print_table("SM83", &results);
+    assert!(failed == 0 || !common::strict(), "{} opkoder feilet", failed);
+}
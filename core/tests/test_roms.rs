@@ -0,0 +1,154 @@
+// Kjører testrom-ene til Blargg og mooneye-test-suite og skriver ut en tabell over resultatene.
+// Katalogene settes med GAMEBOY_BLARGG_ROMS og GAMEBOY_MOONEYE_ROMS.
+// Med GAMEBOY_TEST_ROMS_STRICT feiler testen hvis en ROM feiler.
+mod common;
+
+use std::cell::RefCell;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::rc::Rc;
+
+use common::{CaptureSerial, TestResult};
+use gameboy_core::game_boy::GameBoy;
+use gameboy_core::model::{BootMode, Model};
+
+// Blargg skriver resultatet til 0xa000 når signaturen de b0 61 står i 0xa001-0xa003
+const BLARGG_SIGNATURE: [u8; 3] = [0xde, 0xb0, 0x61];
+const BLARGG_RUNNING: u8 = 0x80;
+// Mooneye kjører LD B,B når testen er ferdig, med Fibonacci-tall i registrene hvis den bestod
+const MOONEYE_BREAKPOINT: u8 = 0x40;
+const MOONEYE_PASSED: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+fn run_rom(path: &Path, model: Option<Model>, run: impl FnOnce(&mut GameBoy, &RefCell<Vec<u8>>) -> (bool, String)) -> (bool, String) {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(error) => return (false, format!("kunne ikke lese: {}", error)),
+    };
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let serial = CaptureSerial { output: output.clone() };
+    // Emulatoren panikker på ting den ikke støtter, og det skal bare gi en feilet ROM
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut builder = GameBoy::builder(data).boot_mode(BootMode::Skip).serial_device(Box::new(serial));
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        match builder.build() {
+            Ok(mut game_boy) => run(&mut game_boy, &output),
+            Err(error) => (false, error.to_string()),
+        }
+    }));
+    result.unwrap_or_else(|payload| (false, common::panic_message(payload)))
+}
+
+fn blargg_memory_text(game_boy: &GameBoy) -> String {
+    (0xa004..0xc000)
+        .map(|address| game_boy.read_memory(address))
+        .take_while(|&byte| byte != 0)
+        .map(|byte| byte as char)
+        .collect()
+}
+
+fn blargg_status(game_boy: &GameBoy, output: &RefCell<Vec<u8>>) -> Option<(bool, String)> {
+    let serial = String::from_utf8_lossy(&output.borrow()).to_string();
+    if serial.contains("Passed") {
+        return Some((true, "Passed (seriell)".to_string()));
+    }
+    if serial.contains("Failed") {
+        return Some((false, summary(&serial)));
+    }
+    let signature = [game_boy.read_memory(0xa001), game_boy.read_memory(0xa002), game_boy.read_memory(0xa003)];
+    if signature != BLARGG_SIGNATURE {
+        return None;
+    }
+    match game_boy.read_memory(0xa000) {
+        BLARGG_RUNNING => None,
+        0 => Some((true, "Passed (0xa000)".to_string())),
+        code => Some((false, format!("kode {:#04x}: {}", code, summary(&blargg_memory_text(game_boy))))),
+    }
+}
+
+fn summary(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(60).collect()
+}
+
+fn run_blargg(path: &Path, frames: u32) -> (bool, String) {
+    run_rom(path, None, |game_boy, output| {
+        for _ in 0..frames {
+            game_boy.run_frame();
+            if let Some(result) = blargg_status(game_boy, output) {
+                return result;
+            }
+        }
+        (false, format!("tidsavbrudd etter {} frames", frames))
+    })
+}
+
+// Filnavn som boot_regs-dmg0.gb og div_timing-C.gb sier hvilken modell testen er laget for
+fn mooneye_model(path: &Path) -> Model {
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+    let suffix = stem.rsplit('-').next().unwrap_or("");
+    if suffix.contains("dmg0") {
+        Model::Dmg0
+    } else if suffix.starts_with("dmg") || suffix.contains("ABC") || suffix == "G" {
+        Model::Dmg
+    } else if suffix.starts_with("mgb") {
+        Model::Mgb
+    } else if suffix.starts_with("sgb") || suffix == "S" {
+        Model::Sgb
+    } else if suffix.starts_with("cgb") || suffix == "C" {
+        Model::Cgb
+    } else {
+        Model::Dmg
+    }
+}
+
+fn run_mooneye(path: &Path, frames: u32) -> (bool, String) {
+    run_rom(path, Some(mooneye_model(path)), |game_boy, _| {
+        let at_breakpoint = |game_boy: &GameBoy| game_boy.read_memory(game_boy.program_counter()) == MOONEYE_BREAKPOINT;
+        for _ in 0..frames {
+            if game_boy.run_frame_until(at_breakpoint).1 {
+                let state = game_boy.cpu_state();
+                let registers = [state.b, state.c, state.d, state.e, state.h, state.l];
+                return if registers == MOONEYE_PASSED {
+                    (true, "Passed".to_string())
+                } else {
+                    (false, format!("B={:02x} C={:02x} D={:02x} E={:02x} H={:02x} L={:02x}", state.b, state.c, state.d, state.e, state.h, state.l))
+                };
+            }
+        }
+        (false, format!("tidsavbrudd etter {} frames", frames))
+    })
+}
+
+#[test]
+#[ignore = "trenger Blargg-ROM-ene i roms_test eller GAMEBOY_BLARGG_ROMS"]
+fn blargg() {
+    let directory = common::data_directory("GAMEBOY_BLARGG_ROMS", "roms_test");
+    let mooneye = common::directory("GAMEBOY_MOONEYE_ROMS", "roms_test/mooneye");
+    let roms = common::find_roms(&directory, &[mooneye]);
+    assert!(!roms.is_empty(), "Ingen ROM-er i {}", directory.display());
+    // cpu_instrs.gb trenger rundt 55 sekunder emulert tid
+    let frames = common::env_number("GAMEBOY_TEST_ROM_FRAMES", 60 * 120);
+    let results: Vec<TestResult> = roms.iter().map(|rom| {
+        let (passed, details) = run_blargg(rom, frames);
+        TestResult { name: common::rom_name(&directory, rom), passed, details }
+    }).collect();
+    let failed = common::print_table("Blargg", &results);
+    assert!(failed == 0 || !common::strict(), "{} Blargg-ROM-er feilet", failed);
+}
+
+#[test]
+#[ignore = "trenger mooneye-test-suite i roms_test/mooneye eller GAMEBOY_MOONEYE_ROMS"]
+fn mooneye() {
+    let directory = common::data_directory("GAMEBOY_MOONEYE_ROMS", "roms_test/mooneye");
+    let roms = common::find_roms(&directory, &[]);
+    assert!(!roms.is_empty(), "Ingen ROM-er i {}", directory.display());
+    let frames = common::env_number("GAMEBOY_TEST_ROM_FRAMES", 60 * 20);
+    let results: Vec<TestResult> = roms.iter().map(|rom| {
+        let (passed, details) = run_mooneye(rom, frames);
+        TestResult { name: common::rom_name(&directory, rom), passed, details }
+    }).collect();
+    let failed = common::print_table("Mooneye", &results);
+    assert!(failed == 0 || !common::strict(), "{} mooneye-ROM-er feilet", failed);
+}
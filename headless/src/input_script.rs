@@ -0,0 +1,33 @@
+use gameboy_core::joypad::JoypadKey;
+
+// Én linje per frame med tastehendelser: `60 +Start` trykker ned Start i frame 60, `70 -Start` slipper den.
+// Flere hendelser kan stå på samme linje, og # starter en kommentar
+pub struct InputEvent {
+    pub frame: u64,
+    pub key: JoypadKey,
+    pub pressed: bool,
+}
+
+pub fn parse(text: &str) -> Result<Vec<InputEvent>, String> {
+    let mut events = vec![];
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(frame) = words.next() else { continue };
+        let frame = frame.parse::<u64>()
+            .map_err(|_| format!("Linje {}: `{}` er ikke et frame-nummer", line_number + 1, frame))?;
+
+        for word in words {
+            let (pressed, name) = match word.split_at_checked(1) {
+                Some(("+", name)) => (true, name),
+                Some(("-", name)) => (false, name),
+                _ => return Err(format!("Linje {}: `{}` må starte med + eller -", line_number + 1, word)),
+            };
+            let key = JoypadKey::from_name(name)
+                .ok_or_else(|| format!("Linje {}: ukjent tast `{}`", line_number + 1, name))?;
+            events.push(InputEvent { frame, key, pressed });
+        }
+    }
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
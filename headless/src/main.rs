@@ -0,0 +1,300 @@
+mod input_script;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::rc::Rc;
+use log::{error, info, LevelFilter};
+use simplelog::{TermLogger, TerminalMode};
+
+use gameboy_core::archive::{self, ArchiveFormat};
+use gameboy_core::game_boy::GameBoy;
+use gameboy_core::model::{BootMode, Model};
+use gameboy_core::movie::Movie;
+use gameboy_core::patch;
+use gameboy_core::palette::Palette;
+use gameboy_core::serial::SerialDevice;
+
+// Samler opp alt spillet sender over link-porten
+struct CaptureSerial {
+    output: Rc<RefCell<Vec<u8>>>,
+    echo: bool,
+}
+
+impl SerialDevice for CaptureSerial {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        self.output.borrow_mut().push(byte);
+        if self.echo {
+            print!("{}", byte as char);
+            let _ = std::io::stdout().flush();
+        }
+        0xff
+    }
+}
+
+fn main() -> ExitCode {
+    TermLogger::init(
+        LevelFilter::Info,
+        simplelog::Config::default(),
+        TerminalMode::Stderr,
+        simplelog::ColorChoice::Auto,
+    )
+        .expect("Kunne ikke sette opp logger");
+
+    let matches = clap::Command::new("gameboy-headless")
+        .version("0.1")
+        .author("Isak Kyrre Lichtwarck Bjugn")
+        .about("Runs a Game Boy ROM without a window, for automation and CI")
+        .arg(clap::Arg::new("cartridge_path")
+            .help("Sets the path to the ROM file to load. Can also be a .zip, .7z or .gz archive, in which case the first ROM in it is used")
+            .required(true))
+        .arg(clap::Arg::new("frames")
+            .help("Maximum number of frames to run")
+            .short('n')
+            .long("frames")
+            .default_value("3600")
+            .value_parser(clap::value_parser!(u64)))
+        .arg(clap::Arg::new("until_serial")
+            .help("Stops when the serial output contains this text")
+            .long("until-serial"))
+        .arg(clap::Arg::new("until_pc")
+            .help("Stops when the program counter reaches this address, e.g. 0x0150")
+            .long("until-pc")
+            .value_parser(parse_number::<u16>))
+        .arg(clap::Arg::new("until_memory")
+            .help("Stops when the memory address has the value, e.g. 0xa000=0x00")
+            .long("until-memory")
+            .value_parser(parse_memory_condition))
+        .arg(clap::Arg::new("input")
+            .help("Input script with one line per frame, e.g. `60 +Start` and `70 -Start`")
+            .short('i')
+            .long("input"))
+        .arg(clap::Arg::new("play")
+            .help("Plays back a movie file: .gbm, .bk2 (BizHawk) or .vbm (VisualBoyAdvance)")
+            .long("play")
+            .value_name("FILE")
+            .conflicts_with("input"))
+        .arg(clap::Arg::new("convert_movie")
+            .help("Converts the movie given with --play to this file, in the format of its extension, and exits")
+            .long("convert-movie")
+            .value_name("FILE")
+            .requires("play"))
+        .arg(clap::Arg::new("screenshot")
+            .help("Writes the final frame to this PNG file")
+            .short('o')
+            .long("screenshot"))
+        .arg(clap::Arg::new("frame_hashes")
+            .help("Writes the CRC32 of every frame to this file")
+            .long("frame-hashes"))
+        .arg(clap::Arg::new("print_serial")
+            .help("Prints the serial output to stdout")
+            .long("print-serial")
+            .action(clap::ArgAction::SetTrue))
+        .arg(clap::Arg::new("palette")
+            .help("Name of the color palette used for the screenshot and hashes")
+            .short('p')
+            .long("palette"))
+        .arg(clap::Arg::new("model")
+            .help("Hardware model to emulate: dmg0, dmg, mgb, sgb or cgb. Defaults to cgb for GBC games and dmg otherwise")
+            .short('m')
+            .long("model")
+            .value_parser(|s: &str| Model::from_name(s).ok_or(format!("Unknown model: {}", s))))
+        .arg(clap::Arg::new("boot_rom")
+            .help("Path to a boot ROM to use instead of the embedded one, which only exists for dmg")
+            .long("boot-rom")
+            .conflicts_with("skip_boot"))
+        .arg(clap::Arg::new("skip_boot")
+            .help("Skips the boot ROM and starts the cartridge directly")
+            .long("skip-boot")
+            .action(clap::ArgAction::SetTrue))
+        .arg(clap::Arg::new("allow_opposite_directions")
+            .help("Lets Left+Right and Up+Down be held at the same time. By default the last pressed direction wins")
+            .long("allow-opposite-directions")
+            .action(clap::ArgAction::SetTrue))
+        .arg(clap::Arg::new("patch")
+            .help("IPS, UPS or BPS patch to apply to the ROM")
+            .long("patch")
+            .value_name("FILE"))
+        .get_matches();
+
+    let cartridge_path = matches.get_one::<String>("cartridge_path").unwrap();
+    let mut cartridge_data = std::fs::read(cartridge_path).expect("Could not read ROM");
+    if ArchiveFormat::detect(&cartridge_data).is_some() {
+        // Uten noen å spørre brukes den første ROM-en i arkivet
+        match archive::rom_names(&cartridge_data).and_then(|names| archive::extract(&cartridge_data, &names[0])) {
+            Ok(rom) => cartridge_data = rom,
+            Err(err) => {
+                error!("{}", err);
+                return ExitCode::from(2)
+            }
+        }
+    }
+    if let Some(path) = matches.get_one::<String>("patch") {
+        let patch = match std::fs::read(path) {
+            Ok(patch) => patch,
+            Err(err) => {
+                error!("Kunne ikke lese patchen {}: {}", path, err);
+                return ExitCode::from(2)
+            }
+        };
+        match patch::apply(&patch, &cartridge_data) {
+            Ok(patched) => cartridge_data = patched,
+            Err(err) => {
+                error!("{}", err);
+                return ExitCode::from(2)
+            }
+        }
+    }
+
+    let input_events = match matches.get_one::<String>("input") {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).expect("Kunne ikke lese input-skriptet");
+            match input_script::parse(&text) {
+                Ok(events) => events,
+                Err(err) => {
+                    error!("{}", err);
+                    return ExitCode::from(2)
+                }
+            }
+        }
+        None => vec![],
+    };
+
+    let boot_mode = match (matches.get_one::<String>("boot_rom"), matches.get_flag("skip_boot")) {
+        (Some(boot_rom_path), _) => BootMode::External(std::fs::read(boot_rom_path).expect("Kunne ikke lese boot-ROM")),
+        (None, true) => BootMode::Skip,
+        (None, false) => BootMode::default(),
+    };
+    let palette = match matches.get_one::<String>("palette") {
+        Some(name) => Palette::by_name(name).unwrap_or_else(|| panic!("Fant ingen palett med navnet `{}`", name)),
+        None => Palette::gray(),
+    };
+    let serial_output = Rc::new(RefCell::new(vec![]));
+
+    let movie = match matches.get_one::<String>("play") {
+        Some(path) => match std::fs::read(path).map_err(|err| err.to_string())
+            .and_then(|bytes| Movie::import(Path::new(path), &bytes, &cartridge_data).map_err(str::to_string)) {
+            Ok(movie) => Some(movie),
+            Err(err) => {
+                error!("Kunne ikke lese filmen {}: {}", path, err);
+                return ExitCode::from(2)
+            }
+        },
+        None => None,
+    };
+    if let (Some(movie), Some(path)) = (&movie, matches.get_one::<String>("convert_movie")) {
+        return match movie.export(Path::new(path), &cartridge_data).map_err(str::to_string)
+            .and_then(|bytes| std::fs::write(path, bytes).map_err(|err| err.to_string())) {
+            Ok(()) => {
+                info!("Skrev {} frames til {}", movie.frames(), path);
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                error!("Kunne ikke skrive {}: {}", path, err);
+                ExitCode::from(2)
+            }
+        }
+    }
+
+    let mut builder = GameBoy::builder(cartridge_data)
+        .boot_mode(boot_mode)
+        .palette(palette)
+        .serial_device(Box::new(CaptureSerial { output: serial_output.clone(), echo: matches.get_flag("print_serial") }));
+    if let Some(model) = matches.get_one::<Model>("model") {
+        builder = builder.model(*model);
+    }
+    if let Some(movie) = movie {
+        builder = builder.play_movie(movie);
+    }
+    let mut game_boy = match builder.build() {
+        Ok(game_boy) => game_boy,
+        Err(error_str) => {
+            error!("{}", error_str);
+            return ExitCode::from(2)
+        }
+    };
+    game_boy.set_allow_opposite_directions(matches.get_flag("allow_opposite_directions"));
+
+    let max_frames = *matches.get_one::<u64>("frames").unwrap();
+    let until_serial = matches.get_one::<String>("until_serial");
+    let until_pc = matches.get_one::<u16>("until_pc").copied();
+    let until_memory = matches.get_one::<(u16, u8)>("until_memory").copied();
+    let has_condition = until_serial.is_some() || until_pc.is_some() || until_memory.is_some();
+
+    let mut frame_hashes = matches.get_one::<String>("frame_hashes")
+        .map(|path| BufWriter::new(File::create(path).expect("Kunne ikke opprette fil for frame-hasher")));
+    let (width, height) = game_boy.screen_size();
+    let mut rgba = vec![0; (width * height * 4) as usize];
+    let mut input_events = input_events.into_iter().peekable();
+
+    let mut frame = 0;
+    let mut condition_met = false;
+    while frame < max_frames && !condition_met {
+        while let Some(event) = input_events.next_if(|event| event.frame <= frame) {
+            match event.pressed {
+                true => game_boy.key_down(event.key),
+                false => game_boy.key_up(event.key),
+            }
+        }
+
+        let (_, stopped) = game_boy.run_frame_until(|game_boy| {
+            until_pc.is_some_and(|pc| game_boy.program_counter() == pc)
+                || until_memory.is_some_and(|(address, value)| game_boy.peek_memory(address) == value)
+        });
+        frame += 1;
+
+        if let Some(ref mut frame_hashes) = frame_hashes {
+            game_boy.write_frame(&mut rgba);
+            writeln!(frame_hashes, "{} {:08x}", frame, crc32fast::hash(&rgba)).expect("Kunne ikke skrive frame-hash");
+        }
+
+        condition_met = stopped || until_serial.is_some_and(|text| {
+            String::from_utf8_lossy(&serial_output.borrow()).contains(text.as_str())
+        });
+    }
+
+    if let Some(path) = matches.get_one::<String>("screenshot") {
+        game_boy.write_frame(&mut rgba);
+        if let Err(err) = write_png(Path::new(path), width, height, &rgba) {
+            error!("Kunne ikke skrive {}: {}", path, err);
+            return ExitCode::from(2)
+        }
+    }
+
+    match (has_condition, condition_met) {
+        (true, true) => {
+            info!("Betingelsen ble oppfylt etter {} frames", frame);
+            ExitCode::SUCCESS
+        }
+        (true, false) => {
+            error!("Betingelsen ble ikke oppfylt innen {} frames", max_frames);
+            ExitCode::FAILURE
+        }
+        (false, _) => {
+            info!("Kjørte {} frames", frame);
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(rgba)
+}
+
+fn parse_number<T: TryFrom<u32>>(s: &str) -> Result<T, String> {
+    let value = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse::<u32>(),
+    }.map_err(|e| format!("Invalid number `{}`: {}", s, e))?;
+    T::try_from(value).map_err(|_| format!("Number out of range: {}", s))
+}
+
+fn parse_memory_condition(s: &str) -> Result<(u16, u8), String> {
+    let (address, value) = s.split_once('=').ok_or(format!("Expected ADDRESS=VALUE, got `{}`", s))?;
+    Ok((parse_number(address.trim())?, parse_number(value.trim())?))
+}
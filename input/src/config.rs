@@ -0,0 +1,124 @@
+use std::path::Path;
+use log::error;
+use toml::{Table, Value};
+use gameboy_core::autofire::InputMacro;
+use crate::Action;
+
+const DEFAULT_CONFIG: &str = include_str!("default_input.toml");
+
+// Hvilken handling hver tast eller knapp er bundet til. Navnene sammenlignes uten å skille på store og små bokstaver
+#[derive(Clone, Debug, Default)]
+pub struct Bindings {
+    entries: Vec<(String, Action)>,
+}
+
+impl Bindings {
+    pub fn action(&self, input: &str) -> Option<Action> {
+        self.entries.iter().find(|(name, _)| name.eq_ignore_ascii_case(input)).map(|(_, action)| *action)
+    }
+    pub fn inputs(&self, action: Action) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter(move |(_, a)| *a == action).map(|(name, _)| name.as_str())
+    }
+    // Handlingene i tabellen erstatter bindingene de hadde fra før
+    fn apply(&mut self, table: &Table, ignored: &[&str], macros: &[(String, InputMacro)]) -> Result<(), String> {
+        for (name, value) in table {
+            if ignored.contains(&name.as_str()) { continue }
+            let action = Action::from_name(name, macros).ok_or(format!("Ukjent handling `{}`", name))?;
+            let inputs = match value {
+                Value::String(input) => vec![input.clone()],
+                Value::Array(inputs) => inputs.iter()
+                    .map(|input| input.as_str().map(str::to_string).ok_or(format!("Ugyldig binding for `{}`", name)))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(format!("Ugyldig binding for `{}`", name)),
+            };
+            self.entries.retain(|(_, a)| *a != action);
+            self.entries.extend(inputs.into_iter().map(|input| (input, action)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GamepadConfig {
+    pub dead_zone: f32,
+    pub bindings: Bindings,
+}
+
+#[derive(Clone, Debug)]
+pub struct InputConfig {
+    pub keyboard: Bindings,
+    pub gamepad: GamepadConfig,
+    // Turbotrykk per sekund
+    pub turbo_rate: u32,
+    pub macros: Vec<(String, InputMacro)>,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        let empty = Self {
+            keyboard: Bindings::default(),
+            gamepad: GamepadConfig { dead_zone: 0.5, bindings: Bindings::default() },
+            turbo_rate: 15,
+            macros: vec![],
+        };
+        empty.merged(DEFAULT_CONFIG).expect("Ugyldig standardoppsett for input")
+    }
+}
+
+impl InputConfig {
+    // Standardoppsettet med endringene fra teksten
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::default().merged(text)
+    }
+    // Bruker standardoppsettet hvis filen ikke finnes eller er ugyldig
+    pub fn load(path: &Path) -> Self {
+        let Ok(text) = std::fs::read_to_string(path) else { return Self::default() };
+        Self::parse(&text).unwrap_or_else(|err| {
+            error!("Kunne ikke lese input-oppsett fra {}: {}", path.display(), err);
+            Self::default()
+        })
+    }
+    // Frames per turbotrykk, regnet ut fra turbo_rate
+    pub fn turbo_period(&self) -> u32 {
+        (60 / self.turbo_rate.max(1)).max(2)
+    }
+    fn merged(mut self, text: &str) -> Result<Self, String> {
+        let table: Table = text.parse().map_err(|err: toml::de::Error| err.message().to_string())?;
+        // Makroene må være kjent før tastene kan bindes til dem
+        if let Some(macros) = table.get("macros") {
+            let Value::Table(macros) = macros else { return Err("`macros` må være en tabell".to_string()) };
+            for (name, steps) in macros {
+                let steps = steps.as_str().ok_or(format!("Makroen `{}` må være en tekst", name))?;
+                let input_macro = InputMacro::parse(steps).map_err(|err| format!("{} (`{}`)", err, name))?;
+                self.macros.retain(|(macro_name, _)| macro_name != name);
+                self.macros.push((name.clone(), input_macro));
+            }
+        }
+        for (section, value) in &table {
+            let Value::Table(values) = value else { return Err(format!("`{}` må være en tabell", section)) };
+            match section.as_str() {
+                "macros" => {}
+                "turbo" => {
+                    for (name, value) in values {
+                        match (name.as_str(), value.as_integer()) {
+                            ("rate", Some(rate)) if (1..=30).contains(&rate) => self.turbo_rate = rate as u32,
+                            ("rate", _) => return Err("`rate` må være et heltall fra 1 til 30".to_string()),
+                            _ => return Err(format!("Ukjent innstilling `{}` i `turbo`", name)),
+                        }
+                    }
+                }
+                "keyboard" => self.keyboard.apply(values, &[], &self.macros)?,
+                "gamepad" => {
+                    if let Some(dead_zone) = values.get("dead_zone") {
+                        let dead_zone = dead_zone.as_float().or(dead_zone.as_integer().map(|value| value as f64))
+                            .ok_or("`dead_zone` må være et tall")?;
+                        self.gamepad.dead_zone = dead_zone.clamp(0.0, 1.0) as f32;
+                    }
+                    self.gamepad.bindings.apply(values, &["dead_zone"], &self.macros)?
+                }
+                _ => return Err(format!("Ukjent seksjon `{}`", section)),
+            }
+        }
+        Ok(self)
+    }
+}
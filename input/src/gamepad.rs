@@ -0,0 +1,95 @@
+use crate::Action;
+use crate::config::GamepadConfig;
+
+// Gjør knapper og akser om til handlinger. En akse gir et trykk når den går forbi dead zone,
+// og slipper når den går tilbake eller over til motsatt retning
+pub struct GamepadMapper {
+    config: GamepadConfig,
+    axis_directions: Vec<(String, i8)>,
+}
+
+impl GamepadMapper {
+    pub fn new(config: GamepadConfig) -> Self {
+        Self { config, axis_directions: vec![] }
+    }
+    pub fn button(&self, button: &str, pressed: bool) -> Option<(Action, bool)> {
+        self.config.bindings.action(button).map(|action| (action, pressed))
+    }
+    pub fn axis(&mut self, axis: &str, value: f32) -> Vec<(Action, bool)> {
+        let direction = match value {
+            value if value > self.config.dead_zone => 1,
+            value if value < -self.config.dead_zone => -1,
+            _ => 0,
+        };
+        let previous = match self.axis_directions.iter_mut().find(|(name, _)| name == axis) {
+            Some((_, previous)) => std::mem::replace(previous, direction),
+            None => {
+                self.axis_directions.push((axis.to_string(), direction));
+                0
+            }
+        };
+        if previous == direction { return vec![] }
+
+        let binding = |direction: i8| {
+            let name = format!("{}{}", axis, if direction > 0 { '+' } else { '-' });
+            self.config.bindings.action(&name)
+        };
+        let mut actions = vec![];
+        if previous != 0 && let Some(action) = binding(previous) {
+            actions.push((action, false));
+        }
+        if direction != 0 && let Some(action) = binding(direction) {
+            actions.push((action, true));
+        }
+        actions
+    }
+}
+
+#[cfg(feature = "gamepad")]
+pub use gilrs_input::Gamepads;
+
+#[cfg(feature = "gamepad")]
+mod gilrs_input {
+    use gilrs::{EventType, Gilrs};
+    use log::{error, info};
+    use crate::Action;
+    use crate::config::GamepadConfig;
+    use super::GamepadMapper;
+
+    // Leser alle tilkoblede gamepads med gilrs
+    pub struct Gamepads {
+        gilrs: Gilrs,
+        mapper: GamepadMapper,
+    }
+
+    impl Gamepads {
+        pub fn new(config: GamepadConfig) -> Option<Self> {
+            match Gilrs::new() {
+                Ok(gilrs) => {
+                    for (_, gamepad) in gilrs.gamepads() {
+                        info!("Gamepad: {}", gamepad.name());
+                    }
+                    Some(Self { gilrs, mapper: GamepadMapper::new(config) })
+                }
+                Err(err) => {
+                    error!("Kunne ikke starte gamepad-støtte: {}", err);
+                    None
+                }
+            }
+        }
+        pub fn poll(&mut self) -> Vec<(Action, bool)> {
+            let mut actions = vec![];
+            while let Some(gilrs::Event { event, .. }) = self.gilrs.next_event() {
+                match event {
+                    EventType::ButtonPressed(button, _) => actions.extend(self.mapper.button(&format!("{:?}", button), true)),
+                    EventType::ButtonReleased(button, _) => actions.extend(self.mapper.button(&format!("{:?}", button), false)),
+                    EventType::AxisChanged(axis, value, _) => actions.extend(self.mapper.axis(&format!("{:?}", axis), value)),
+                    EventType::Connected => info!("Gamepad koblet til"),
+                    EventType::Disconnected => info!("Gamepad koblet fra"),
+                    _ => {}
+                }
+            }
+            actions
+        }
+    }
+}
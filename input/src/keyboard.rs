@@ -0,0 +1,10 @@
+use winit::keyboard::Key;
+
+// Navnet en tast har i input-oppsettet: tegnet for vanlige taster og winit-navnet for resten
+pub fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(character) => Some(character.to_string()),
+        Key::Named(named_key) => Some(format!("{:?}", named_key)),
+        _ => None,
+    }
+}
@@ -0,0 +1,76 @@
+pub mod config;
+pub mod keyboard;
+pub mod gamepad;
+
+use gameboy_core::autofire::InputMacro;
+use gameboy_core::joypad::JoypadKey;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hotkey {
+    Quit,
+    Pause,
+    FrameAdvance,
+    FastForward,
+    SpeedUp,
+    SpeedDown,
+    NextPalette,
+    Screenshot,
+    SaveState,
+    LoadState,
+    Rewind,
+    RecordMacro,
+    PlayMacro,
+    ReloadCheats,
+}
+
+impl Hotkey {
+    pub const ALL: [Hotkey; 14] = [Hotkey::Quit, Hotkey::Pause, Hotkey::FrameAdvance, Hotkey::FastForward, Hotkey::SpeedUp,
+        Hotkey::SpeedDown, Hotkey::NextPalette, Hotkey::Screenshot, Hotkey::SaveState, Hotkey::LoadState, Hotkey::Rewind,
+        Hotkey::RecordMacro, Hotkey::PlayMacro, Hotkey::ReloadCheats];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Hotkey::ALL.into_iter().find(|hotkey| hotkey.name().eq_ignore_ascii_case(name))
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hotkey::Quit => "quit",
+            Hotkey::Pause => "pause",
+            Hotkey::FrameAdvance => "frame_advance",
+            Hotkey::FastForward => "fast_forward",
+            Hotkey::SpeedUp => "speed_up",
+            Hotkey::SpeedDown => "speed_down",
+            Hotkey::NextPalette => "next_palette",
+            Hotkey::Screenshot => "screenshot",
+            Hotkey::SaveState => "save_state",
+            Hotkey::LoadState => "load_state",
+            Hotkey::Rewind => "rewind",
+            Hotkey::RecordMacro => "record_macro",
+            Hotkey::PlayMacro => "play_macro",
+            Hotkey::ReloadCheats => "reload_cheats",
+        }
+    }
+}
+
+// Det en tast eller knapp på en gamepad kan være bundet til
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Joypad(JoypadKey),
+    Hotkey(Hotkey),
+    // Tasten trykkes av og på så lenge knappen holdes
+    Turbo(JoypadKey),
+    // Indeks i InputConfig::macros
+    Macro(usize),
+}
+
+impl Action {
+    // Turbo skrives som `turbo_a`. Makroer bindes med navnet de har i `[macros]`
+    pub fn from_name(name: &str, macros: &[(String, InputMacro)]) -> Option<Self> {
+        JoypadKey::from_name(name).map(Action::Joypad)
+            .or_else(|| Hotkey::from_name(name).map(Action::Hotkey))
+            .or_else(|| {
+                let key = name.get(..6).filter(|prefix| prefix.eq_ignore_ascii_case("turbo_")).and(name.get(6..))?;
+                JoypadKey::from_name(key).map(Action::Turbo)
+            })
+            .or_else(|| macros.iter().position(|(macro_name, _)| macro_name.eq_ignore_ascii_case(name)).map(Action::Macro))
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use log::info;
+use pixels::{Error, Pixels, SurfaceTexture};
+use winit::dpi::LogicalSize;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Window, WindowId};
+
+use gameboy_core::game_boy::GameBoy;
+use gameboy_core::palette::Palette;
+use gameboy_core::ppu::viewer::{TileMap, OAM_VIEWER_HEIGHT, OAM_VIEWER_WIDTH, TILE_MAP_VIEWER_SIZE, TILE_VIEWER_HEIGHT, TILE_VIEWER_WIDTH};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Viewer {
+    Tiles,
+    TileMaps,
+    Oam,
+}
+
+impl Viewer {
+    fn title(&self) -> &'static str {
+        match self {
+            Viewer::Tiles => "Tiles (VRAM)",
+            Viewer::TileMaps => "Bakgrunnskart (0x9800 og 0x9c00)",
+            Viewer::Oam => "OAM",
+        }
+    }
+    fn size(&self, game_boy: &GameBoy) -> (usize, usize) {
+        match self {
+            Viewer::Tiles => (TILE_VIEWER_WIDTH * game_boy.video_ram_banks(), TILE_VIEWER_HEIGHT),
+            // De to kartene vises ved siden av hverandre
+            Viewer::TileMaps => (TILE_MAP_VIEWER_SIZE * 2, TILE_MAP_VIEWER_SIZE),
+            Viewer::Oam => (OAM_VIEWER_WIDTH, OAM_VIEWER_HEIGHT),
+        }
+    }
+}
+
+pub struct DebugWindow {
+    pub viewer: Viewer,
+    window: Arc<Window>,
+    pixels: Pixels<'static>,
+}
+
+impl DebugWindow {
+    pub fn open(elwt: &ActiveEventLoop, viewer: Viewer, scale: u8, game_boy: &GameBoy) -> Result<Self, Error> {
+        let (width, height) = viewer.size(game_boy);
+        let size = LogicalSize::new(width as f64 * scale as f64, height as f64 * scale as f64);
+        let window = Arc::new(elwt.create_window(
+            Window::default_attributes()
+                .with_title(viewer.title())
+                .with_inner_size(size)
+                .with_min_inner_size(size)
+        ).map_err(|e| Error::UserDefined(Box::new(e)))?);
+
+        let pixels = {
+            let window_size = window.inner_size();
+            let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, window.clone());
+            Pixels::new(width as u32, height as u32, surface_texture)?
+        };
+
+        if viewer == Viewer::Oam {
+            log_oam_entries(game_boy);
+        }
+        Ok(Self { viewer, window, pixels })
+    }
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+    pub fn render(&mut self, game_boy: &GameBoy, palette: &Palette) -> Result<(), Error> {
+        let frame = self.pixels.frame_mut();
+        match self.viewer {
+            Viewer::Tiles => frame.copy_from_slice(&game_boy.tile_data_image(palette)),
+            Viewer::TileMaps => {
+                let row_length = TILE_MAP_VIEWER_SIZE * 4;
+                for (i, tile_map) in [TileMap::Low, TileMap::High].into_iter().enumerate() {
+                    let image = game_boy.tile_map_image(tile_map, palette);
+                    for (y, row) in image.chunks_exact(row_length).enumerate() {
+                        let start = y * row_length * 2 + i * row_length;
+                        frame[start..start + row_length].copy_from_slice(row);
+                    }
+                }
+            }
+            Viewer::Oam => frame.copy_from_slice(&game_boy.oam_image(palette)),
+        }
+        self.pixels.render()
+    }
+}
+
+fn log_oam_entries(game_boy: &GameBoy) {
+    info!("OAM:  #    y    x  tile  flagg");
+    for (i, sprite) in game_boy.oam_entries().iter().enumerate() {
+        info!("OAM: {:2} {:4} {:4}  {:#04x}  {:?}", i, sprite.y as i8, sprite.x as i8, sprite.tile_index, sprite.flags);
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use gameboy_core::game_boy::GameBoy;
+use gameboy_core::ram_search::{RamSearch, SearchFilter, ValueType};
+
+const HELP: &str = "\
+Kommandoer (adresser skrives heksadesimalt, verdier desimalt eller med 0x foran):
+  regs                  Viser registrene
+  peek <adresse>        Leser en byte
+  poke <adresse> <verdi>
+                        Skriver en byte
+  search [u8|u16|bcd8|bcd16]
+                        Starter et nytt RAM-søk i WRAM, HRAM og SRAM
+  filter <eq|ne|inc|dec|verdi>
+                        Beholder treffene som er uendret, endret, økt, minket eller lik verdien
+  list [antall]         Viser treffene med verdien nå og ved forrige filtrering
+  cheat <adresse> <verdi>
+                        Lager GameShark-koder som holder verdien fast, med typen fra søket";
+
+fn parse_address(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_value(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn movie_active(game_boy: &GameBoy) -> bool {
+    game_boy.recording_movie() || game_boy.playing_movie()
+}
+
+// Leser kommandoer fra stdin i en egen tråd, og kjører dem mellom frames
+pub struct Debugger {
+    commands: Receiver<String>,
+    search: Option<RamSearch>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() { break }
+            }
+        });
+        println!("Feilsøkeren er klar. Skriv `help` for en oversikt");
+        Self { commands, search: None }
+    }
+    pub fn poll(&mut self, game_boy: &mut GameBoy) {
+        while let Ok(line) = self.commands.try_recv() {
+            if let Err(err) = self.execute(game_boy, &line) {
+                println!("{}", err);
+            }
+        }
+    }
+    fn execute(&mut self, game_boy: &mut GameBoy, line: &str) -> Result<(), &'static str> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["help"] => println!("{}", HELP),
+            ["regs"] => println!("{:02x?}", game_boy.cpu_state()),
+            ["peek", address] => {
+                let address = parse_address(address).ok_or("Ugyldig adresse")?;
+                println!("{:#06x}: {:#04x}", address, game_boy.peek_memory(address));
+            }
+            // Filmer spiller ikke av endringer i minnet eller juksekodene
+            ["poke", ..] if movie_active(game_boy) => return Err("Minnet kan ikke endres mens en film spilles inn eller av"),
+            ["poke", address, value] => {
+                let address = parse_address(address).ok_or("Ugyldig adresse")?;
+                let value = parse_value(value).and_then(|value| u8::try_from(value).ok()).ok_or("Ugyldig verdi")?;
+                game_boy.write_memory(address, value);
+            }
+            ["search", value_type @ ..] => {
+                let value_type = match value_type {
+                    [] => ValueType::U8,
+                    [name] => ValueType::from_name(name).ok_or("Ukjent type. Bruk u8, u16, bcd8 eller bcd16")?,
+                    _ => return Err("Bruk: search [u8|u16|bcd8|bcd16]"),
+                };
+                let search = RamSearch::new(game_boy, value_type);
+                println!("Søker etter {} i {} adresser", value_type.name(), search.len());
+                self.search = Some(search);
+            }
+            ["filter", filter] => {
+                let filter = SearchFilter::parse(filter).ok_or("Ukjent filter. Bruk eq, ne, inc, dec eller en verdi")?;
+                let search = self.search.as_mut().ok_or("Start et søk med `search` først")?;
+                search.filter(game_boy, filter);
+                println!("{} treff", search.len());
+            }
+            ["list", limit @ ..] => {
+                let limit = match limit {
+                    [] => 20,
+                    [limit] => limit.parse().map_err(|_| "Ugyldig antall")?,
+                    _ => return Err("Bruk: list [antall]"),
+                };
+                let search = self.search.as_ref().ok_or("Start et søk med `search` først")?;
+                for result in search.results(game_boy, limit) {
+                    println!("{:#06x}  {:>5}  (forrige {})", result.address, result.value, result.previous);
+                }
+                if search.len() > limit {
+                    println!("... og {} til", search.len() - limit);
+                }
+            }
+            ["cheat", ..] if movie_active(game_boy) => return Err("Juksekodene kan ikke endres mens en film spilles inn eller av"),
+            ["cheat", address, value] => {
+                let address = parse_address(address).ok_or("Ugyldig adresse")?;
+                let value = parse_value(value).ok_or("Ugyldig verdi")?;
+                let value_type = self.search.as_ref().map_or(ValueType::U8, RamSearch::value_type);
+                let cheats = value_type.cheats(address, value)?;
+                for cheat in cheats {
+                    println!("{} {}", cheat.code, cheat.description);
+                    game_boy.cheats_mut().add(cheat);
+                }
+            }
+            _ => return Err("Ukjent kommando. Skriv `help` for en oversikt"),
+        }
+        Ok(())
+    }
+}
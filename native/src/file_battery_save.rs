@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::path::PathBuf;
 use gameboy_core::battery_save::BatterySave;
 
@@ -11,13 +11,19 @@ impl FileBatterySave {
     pub fn new(cartridge_path: PathBuf) -> Self {
         Self { battery_save_path: cartridge_path.with_extension("gbsave")}
     }
+    // En patchet ROM får egen lagring, med CRC32 til den patchede ROM-en i navnet
+    pub fn patched(cartridge_path: PathBuf, rom_crc32: u32) -> Self {
+        let stem = cartridge_path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        Self { battery_save_path: cartridge_path.with_file_name(format!("{}-{:08x}.gbsave", stem, rom_crc32)) }
+    }
 }
 
 impl BatterySave for FileBatterySave {
-    fn load(&self, ram: &mut [u8]) {
-        if let Ok(mut file) = File::open(self.battery_save_path.clone()) {
-            file.read_exact(ram).expect("Failed to read battery data")
-        }
+    fn load(&self, ram: &mut [u8]) -> usize {
+        let Ok(data) = std::fs::read(&self.battery_save_path) else { return 0 };
+        let length = data.len().min(ram.len());
+        ram[..length].copy_from_slice(&data[..length]);
+        length
     }
 
     fn save(&self, data: &[u8]) {
@@ -25,4 +31,22 @@ impl BatterySave for FileBatterySave {
             .and_then(|mut file| file.write_all(data))
             .expect("Failed to save battery data");
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gameboy_core::rtc::RTC_STATE_SIZE;
+
+    // Lagringer fra før RTC-en ble lagret er bare RAM-en på 32 KiB
+    #[test]
+    fn loads_save_without_rtc() {
+        let cartridge_path = std::env::temp_dir().join(format!("gameboy-test-{}.gb", std::process::id()));
+        std::fs::write(cartridge_path.with_extension("gbsave"), vec![0x34; 0x8000]).unwrap();
+        let mut save = vec![0; 0x8000 + RTC_STATE_SIZE];
+        let length = FileBatterySave::new(cartridge_path.clone()).load(&mut save);
+        std::fs::remove_file(cartridge_path.with_extension("gbsave")).unwrap();
+        assert_eq!(length, 0x8000);
+        assert_eq!(save[0x7fff], 0x34);
+        assert_eq!(save[0x8000], 0);
+    }
+}
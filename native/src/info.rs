@@ -0,0 +1,49 @@
+use gameboy_core::header::{self, CartridgeHeader};
+
+fn check(valid: bool) -> &'static str {
+    if valid { "OK" } else { "FEIL" }
+}
+
+fn size(bytes: usize) -> String {
+    match bytes {
+        0 => "ingen".to_string(),
+        bytes if bytes >= 0x100000 => format!("{} MiB", bytes / 0x100000),
+        bytes => format!("{} KiB", bytes / 0x400),
+    }
+}
+
+// Skriver ut headeren og sjekker den. Gir false hvis en ekte Game Boy ikke ville startet spillet
+pub fn print_info(rom: &[u8]) -> Result<bool, &'static str> {
+    let header = CartridgeHeader::new(rom)?;
+    let global_checksum = header::global_checksum(rom);
+
+    println!("Tittel:              {}", header.title());
+    println!("Produsentkode:       {}", header.manufacturer_code().as_deref().unwrap_or("-"));
+    println!("GBC:                 {:#04x} ({})", header.cgb_flag(), match header.cgb_flag() {
+        0xc0 => "kun GBC",
+        0x80 => "GBC, kjører også på DMG",
+        _ => "nei",
+    });
+    println!("SGB:                 {:#04x} ({})", header.sgb_flag(), if header.sgb_support() { "ja" } else { "nei" });
+    println!("Gammel lisenskode:   {:#04x}", header.old_licensee_code());
+    println!("Ny lisenskode:       {}", header.new_licensee_code().escape_debug());
+    println!("Lisensinnehaver:     {}", header.licensee().unwrap_or("ukjent"));
+    println!("Kassettype:          {:#04x} ({}{})", header.cartridge_type(), header.mapper().unwrap_or("ukjent"),
+        if header.mapper_supported() { "" } else { ", støttes ikke" });
+    println!("ROM-størrelse:       {:#04x} ({})", header.rom_size_code(), match header.rom_size() {
+        Some(bytes) if bytes == rom.len() => size(bytes),
+        Some(bytes) => format!("{}, men filen er {} byte", size(bytes), rom.len()),
+        None => "ukjent".to_string(),
+    });
+    println!("RAM-størrelse:       {:#04x} ({})", header.ram_size_code(), header.ram_size().map_or("ukjent".to_string(), size));
+    println!("Versjon:             {}", header.version());
+    println!("Nintendo-logo:       {}", check(header.logo_valid()));
+    println!("Header-sjekksum:     {:#04x} ({}, regnet ut {:#04x})",
+        header.header_checksum(), check(header.header_checksum_valid()), header.computed_header_checksum());
+    println!("Global sjekksum:     {:#06x} ({}, regnet ut {:#06x})",
+        header.global_checksum(), check(header.global_checksum() == global_checksum), global_checksum);
+    println!("CRC32:               {:08X}", crc32fast::hash(rom));
+    println!("SHA-1:               {}", header::sha1_hex(rom));
+
+    Ok(header.logo_valid() && header.header_checksum_valid())
+}
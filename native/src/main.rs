@@ -1,16 +1,33 @@
 mod file_battery_save;
+mod debug_window;
+mod debugger;
+mod info;
 
 use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
-use log::{error, LevelFilter};
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{error, info, warn, LevelFilter};
 use pixels::Error;
 use simplelog::{TermLogger, TerminalMode};
 
-use gameboy_core::frame_buffer::FrameBuffer;
+use gameboy_core::archive::{self, ArchiveFormat};
+use gameboy_core::autofire::InputMacro;
+use gameboy_core::cheats::Cheats;
 use gameboy_core::game_boy::GameBoy;
-use gameboy_core::joypad::JoypadKey;
-use gameboy_core::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use gameboy_core::model::{BootMode, Model};
+use gameboy_core::movie::{Movie, MovieFormat};
+use gameboy_core::pacing::{FramePacer, Speed};
+use gameboy_core::patch::{self, PatchFormat};
+use gameboy_core::palette::Palette;
+use gameboy_core::ppu::layer_mask::Layers;
+use gameboy_input::{Action, Hotkey};
+use gameboy_input::config::InputConfig;
+use crate::debugger::Debugger;
+#[cfg(feature = "gamepad")]
+use gameboy_input::gamepad::Gamepads;
+use gameboy_input::keyboard::key_name;
+use crate::debug_window::{DebugWindow, Viewer};
 use crate::file_battery_save::FileBatterySave;
 
 fn main() -> Result<(), Error> {
@@ -26,8 +43,15 @@ fn main() -> Result<(), Error> {
         .version("0.1")
         .author("Isak Kyrre Lichtwarck Bjugn")
         .about("A Gameboy emulator written in Rust")
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(clap::Command::new("info")
+            .about("Prints the cartridge header and checks the logo and checksums. Exits with 1 if a Game Boy would refuse to boot the ROM")
+            .arg(clap::Arg::new("cartridge_path")
+                .help("Path to the ROM file. Can also be a .zip, .7z or .gz archive")
+                .required(true)))
         .arg(clap::Arg::new("cartridge_path")
-            .help("Sets the path to the ROM file to load")
+            .help("Sets the path to the ROM file to load. Can also be a .zip, .7z or .gz archive")
             .required(true))
         .arg(clap::Arg::new("scale")
             .help("Scales the display. Default is 2")
@@ -38,23 +62,230 @@ fn main() -> Result<(), Error> {
                 s.parse::<u8>()
                     .map_err(|e| format!("Invalid scale value: {}", e))
             }))
+        .arg(clap::Arg::new("palette")
+            .help("Name of the color palette to start with. Press P to cycle through palettes")
+            .short('p')
+            .long("palette"))
+        .arg(clap::Arg::new("palettes")
+            .help("File with custom palettes, one per line: `Name = RRGGBB RRGGBB RRGGBB RRGGBB`")
+            .long("palettes")
+            .default_value("palettes.txt"))
+        .arg(clap::Arg::new("input_config")
+            .help("TOML file with keyboard, hotkey and gamepad bindings")
+            .long("input-config")
+            .default_value("input.toml"))
+        .arg(clap::Arg::new("allow_opposite_directions")
+            .help("Lets Left+Right and Up+Down be held at the same time. By default the last pressed direction wins")
+            .long("allow-opposite-directions")
+            .action(clap::ArgAction::SetTrue))
+        .arg(clap::Arg::new("cheats")
+            .help("File with GameShark and Game Genie codes, one per line. Defaults to the ROM path with .cht as extension")
+            .long("cheats")
+            .value_name("FILE"))
+        .arg(clap::Arg::new("debugger")
+            .help("Reads debugger commands from stdin, like RAM search and memory peek/poke. Type `help` for a list")
+            .long("debugger")
+            .action(clap::ArgAction::SetTrue))
+        .arg(clap::Arg::new("patch")
+            .help("IPS, UPS or BPS patch to apply to the ROM. Defaults to a .ips, .ups or .bps file next to the ROM")
+            .long("patch")
+            .value_name("FILE"))
+        .arg(clap::Arg::new("model")
+            .help("Hardware model to emulate: dmg0, dmg, mgb, sgb or cgb. Defaults to cgb for GBC games and dmg otherwise")
+            .short('m')
+            .long("model")
+            .value_parser(|s: &str| Model::from_name(s).ok_or(format!("Unknown model: {}", s))))
+        .arg(clap::Arg::new("boot_rom")
+            .help("Path to a boot ROM to use instead of the embedded one, which only exists for dmg")
+            .long("boot-rom")
+            .conflicts_with("skip_boot"))
+        .arg(clap::Arg::new("skip_boot")
+            .help("Skips the boot ROM and starts the cartridge directly")
+            .long("skip-boot")
+            .action(clap::ArgAction::SetTrue))
+        .arg(clap::Arg::new("record")
+            .help("Records all input to a movie file that is written on exit. The format follows the extension: .gbm, .bk2 (BizHawk) or .vbm (VisualBoyAdvance)")
+            .long("record")
+            .value_name("FILE")
+            .conflicts_with("play"))
+        .arg(clap::Arg::new("play")
+            .help("Plays back a movie file (.gbm, .bk2 or .vbm). Keyboard input is ignored until the movie ends")
+            .long("play")
+            .value_name("FILE"))
         .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("info") {
+        let path = matches.get_one::<String>("cartridge_path").unwrap();
+        let mut data = std::fs::read(path).unwrap_or_else(|err| panic!("Kunne ikke lese {}: {}", path, err));
+        if ArchiveFormat::detect(&data).is_some() {
+            (data, _) = unpack_archive(&data).unwrap_or_else(|err| {
+                error!("{}", err);
+                std::process::exit(2)
+            });
+        }
+        match info::print_info(&data) {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                error!("{}", err);
+                std::process::exit(2)
+            }
+        }
+    }
+
     let scale = matches.get_one::<u8>("scale").copied().unwrap();
     let cartridge_path = PathBuf::from(matches.get_one::<String>("cartridge_path").unwrap());
-    let file_battery_save = FileBatterySave::new(cartridge_path.clone());
     let mut cartridge_data = vec![];
     File::open(&cartridge_path).and_then(|mut f| f.read_to_end(&mut cartridge_data)).expect("Could not read ROM");
+    // Lagringen er oppkalt etter denne. Har arkivet flere ROM-er, får hver av dem sin egen
+    let mut save_path = cartridge_path.clone();
+    if ArchiveFormat::detect(&cartridge_data).is_some() {
+        let entry_name;
+        (cartridge_data, entry_name) = unpack_archive(&cartridge_data).unwrap_or_else(|err| {
+            error!("{}", err);
+            std::process::exit(2)
+        });
+        if let Some(entry_name) = entry_name {
+            let stem = |path: &Path| path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            save_path = cartridge_path.with_file_name(format!("{}-{}.gb", stem(&cartridge_path), stem(Path::new(&entry_name))));
+        }
+    }
+    let patch_path = matches.get_one::<String>("patch").map(PathBuf::from).or_else(|| {
+        PatchFormat::ALL.iter().map(|format| cartridge_path.with_extension(format.extension())).find(|path| path.exists())
+    });
+    let file_battery_save = match patch_path {
+        Some(ref patch_path) => {
+            let patch = std::fs::read(patch_path).unwrap_or_else(|err| {
+                error!("Kunne ikke lese patchen {}: {}", patch_path.display(), err);
+                std::process::exit(2)
+            });
+            cartridge_data = patch::apply(&patch, &cartridge_data).unwrap_or_else(|err| {
+                error!("Kunne ikke bruke patchen {}: {}", patch_path.display(), err);
+                std::process::exit(2)
+            });
+            info!("Bruker patchen {}", patch_path.display());
+            FileBatterySave::patched(save_path, crc32fast::hash(&cartridge_data))
+        }
+        None => FileBatterySave::new(save_path),
+    };
+
+    let boot_mode = match (matches.get_one::<String>("boot_rom"), matches.get_flag("skip_boot")) {
+        (Some(boot_rom_path), _) => BootMode::External(std::fs::read(boot_rom_path).expect("Kunne ikke lese boot-ROM")),
+        (None, true) => BootMode::Skip,
+        (None, false) => BootMode::default(),
+    };
+
+    let palettes = load_palettes(&PathBuf::from(matches.get_one::<String>("palettes").unwrap()));
+    let palette_index = match matches.get_one::<String>("palette") {
+        Some(name) => palettes.iter()
+            .position(|palette| palette.name.eq_ignore_ascii_case(name))
+            .unwrap_or_else(|| panic!("Fant ingen palett med navnet `{}`", name)),
+        None => 0,
+    };
 
-    let game_boy = match GameBoy::new(cartridge_data, Some(Box::new(file_battery_save))) {
+    let input_config = InputConfig::load(Path::new(matches.get_one::<String>("input_config").unwrap()));
+
+    let cheats_path = matches.get_one::<String>("cheats").map(PathBuf::from).unwrap_or_else(|| cartridge_path.with_extension("cht"));
+    let cheats = read_cheats(&cheats_path).unwrap_or_default();
+
+    let rom = cartridge_data.clone();
+    let mut builder = GameBoy::builder(cartridge_data)
+        .boot_mode(boot_mode)
+        .palette(palettes[palette_index].clone())
+        .battery_save(Box::new(file_battery_save))
+        .cheats(cheats.clone());
+    if let Some(model) = matches.get_one::<Model>("model") {
+        builder = builder.model(*model);
+    }
+    let record_path = matches.get_one::<String>("record").map(PathBuf::from);
+    if let Some(ref path) = record_path {
+        if MovieFormat::from_path(path) != MovieFormat::Gbm && cheats.list().iter().any(|cheat| cheat.enabled) {
+            error!("Bare .gbm-filmer kan lagre juksekoder. Slå dem av, eller spill inn til en .gbm-fil");
+            std::process::exit(2)
+        }
+        builder = builder.record_movie();
+    }
+    if let Some(play_path) = matches.get_one::<String>("play") {
+        let movie = std::fs::read(play_path).map_err(|err| err.to_string())
+            .and_then(|bytes| Movie::import(Path::new(play_path), &bytes, &rom).map_err(str::to_string))
+            .unwrap_or_else(|err| panic!("Kunne ikke lese filmen {}: {}", play_path, err));
+        info!("Spiller av {} tastetrykk over {} frames", movie.events.len(), movie.frames());
+        builder = builder.play_movie(movie);
+    }
+    let mut game_boy = match builder.build() {
         Ok(game_boy) => game_boy,
         Err(error_str) => panic!("{}", error_str),
     };
+    game_boy.set_allow_opposite_directions(matches.get_flag("allow_opposite_directions"));
 
-    run_game_loop(game_boy, scale)
+    let debugger = matches.get_flag("debugger").then(Debugger::new);
+    run_game_loop(game_boy, scale, palettes, input_config, cheats_path, debugger, record_path.map(|path| (path, rom)))
+}
+
+// Bruker ROM-en i arkivet, eller spør hvilken som skal brukes når det er flere.
+// Gir også navnet på ROM-en som ble valgt, hvis arkivet har flere
+fn unpack_archive(data: &[u8]) -> Result<(Vec<u8>, Option<String>), &'static str> {
+    let names = archive::rom_names(data)?;
+    let name = match names.as_slice() {
+        [name] => name,
+        _ => {
+            for (i, name) in names.iter().enumerate() {
+                println!("{}: {}", i + 1, name);
+            }
+            loop {
+                print!("Velg ROM (1-{}): ", names.len());
+                std::io::stdout().flush().unwrap();
+                let line = std::io::stdin().lock().lines().next()
+                    .and_then(Result::ok)
+                    .ok_or("Fikk ikke valgt ROM")?;
+                match line.trim().parse::<usize>() {
+                    Ok(number @ 1..) if number <= names.len() => break &names[number - 1],
+                    _ => println!("Ugyldig valg"),
+                }
+            }
+        }
+    };
+    info!("Pakker ut {}", name);
+    Ok((archive::extract(data, name)?, (names.len() > 1).then(|| name.clone())))
+}
+
+// None hvis filen ikke finnes eller er ugyldig
+fn read_cheats(path: &Path) -> Option<Cheats> {
+    let text = std::fs::read_to_string(path).ok()?;
+    match Cheats::parse(&text) {
+        Ok(cheats) => {
+            info!("{} av {} juksekoder er på", cheats.list().iter().filter(|cheat| cheat.enabled).count(), cheats.list().len());
+            Some(cheats)
+        }
+        Err(err) => {
+            error!("Kunne ikke lese juksekodene i {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+// Beholder juksekodene som er lastet hvis filen ikke finnes eller er ugyldig.
+// Filmer har juksekodene fra start, så de kan ikke endres under innspilling eller avspilling
+fn load_cheats(game_boy: &mut GameBoy, path: &Path) {
+    if game_boy.recording_movie() || game_boy.playing_movie() {
+        warn!("Juksekodene kan ikke endres mens en film spilles inn eller av");
+    } else if let Some(cheats) = read_cheats(path) {
+        *game_boy.cheats_mut() = cheats;
+    }
 }
 
-fn run_game_loop(mut game_boy: Box<GameBoy>, scale: u8) -> Result<(), Error> {
+fn load_palettes(path: &PathBuf) -> Vec<Palette> {
+    let mut palettes = Palette::presets();
+    if let Ok(text) = std::fs::read_to_string(path) {
+        match Palette::parse_list(&text) {
+            Ok(custom_palettes) => palettes.extend(custom_palettes),
+            Err(err) => error!("Kunne ikke lese paletter fra {}: {}", path.display(), err),
+        }
+    }
+    palettes
+}
+
+fn run_game_loop(mut game_boy: Box<GameBoy>, scale: u8, palettes: Vec<Palette>, input_config: InputConfig, cheats_path: PathBuf, mut debugger: Option<Debugger>, record: Option<(PathBuf, Vec<u8>)>) -> Result<(), Error> {
     use std::thread;
     use std::time::{Duration, Instant};
     use pixels::{Error, Pixels, SurfaceTexture};
@@ -64,7 +295,8 @@ fn run_game_loop(mut game_boy: Box<GameBoy>, scale: u8) -> Result<(), Error> {
 
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
-    let size = LogicalSize::new(SCREEN_WIDTH as f64 * scale as f64, SCREEN_HEIGHT as f64 * scale as f64);
+    let (screen_width, screen_height) = game_boy.screen_size();
+    let size = LogicalSize::new(screen_width as f64 * scale as f64, screen_height as f64 * scale as f64);
 
     let window = event_loop.create_window(
         Window::default_attributes()
@@ -76,79 +308,189 @@ fn run_game_loop(mut game_boy: Box<GameBoy>, scale: u8) -> Result<(), Error> {
     let mut pixels = {
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(SCREEN_WIDTH, SCREEN_HEIGHT, surface_texture)?
+        Pixels::new(screen_width, screen_height, surface_texture)?
     };
 
-    let frame_duration = Duration::from_millis(16);
-    let cpu_cycles_per_frame = (4194204f64 / 1000.0 * 16.0).round() as u32;
-    let mut cpu_cycles = 0;
+    let mut palette_index = palettes.iter().position(|palette| palette.name == game_boy.palette().name).unwrap_or(0);
+    let clock_start = Instant::now();
+    let mut frame_pacer = FramePacer::new();
+    let mut speed_before_turbo = frame_pacer.speed();
+    let mut debug_windows: Vec<DebugWindow> = vec![];
+    let mut shift_pressed = false;
+    let mut recorded_macro: Option<InputMacro> = None;
+    game_boy.set_turbo_period(input_config.turbo_period());
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = Gamepads::new(input_config.gamepad.clone());
 
     let res = event_loop.run(|event, elwt| {
         use winit::event::{Event, WindowEvent};
-        use winit::event::ElementState::{Pressed, Released};
+        use winit::event::ElementState::Pressed;
         use winit::keyboard::{Key, NamedKey};
 
-        let start = Instant::now();
-
-        while cpu_cycles < cpu_cycles_per_frame {
-            cpu_cycles += game_boy.emulate();
+        game_boy.run_frames(frame_pacer.frames_due(clock_start.elapsed().as_secs_f64()));
+        if let Some(ref mut debugger) = debugger {
+            debugger.poll(&mut game_boy);
         }
 
-        cpu_cycles -= cpu_cycles_per_frame;
-
-        if let Some(data) = game_boy.updated_frame_buffer() {
-            data.write_to_rbga_buffer(pixels.frame_mut());
-            if let Err(err) = pixels.render() {
-                error!("Feil under tegning til skjerm!");
-                elwt.exit();
+        let frame_updated = game_boy.write_updated_frame(pixels.frame_mut());
+        if frame_updated && let Err(err) = pixels.render() {
+            error!("Feil under tegning til skjerm!");
+            elwt.exit();
+        }
+        if frame_updated {
+            for debug_window in debug_windows.iter_mut() {
+                if let Err(err) = debug_window.render(&game_boy, game_boy.palette()) {
+                    error!("Feil under tegning av feilsøkingsvindu: {}", err);
+                }
             }
         }
 
+        let mut actions = vec![];
         if let Event::WindowEvent { event: WindowEvent::KeyboardInput { event: key_event, .. }, .. } = &event {
             match (key_event.state, key_event.logical_key.as_ref()) {
-                (Pressed, Key::Named(NamedKey::Escape)) => {
-                    elwt.exit();
-                    window.request_redraw();
+                (Pressed, Key::Named(named_key @ (NamedKey::F2 | NamedKey::F3 | NamedKey::F4))) if !key_event.repeat => {
+                    let viewer = match named_key {
+                        NamedKey::F2 => Viewer::Tiles,
+                        NamedKey::F3 => Viewer::TileMaps,
+                        _ => Viewer::Oam,
+                    };
+                    toggle_debug_window(&mut debug_windows, viewer, elwt, scale, &game_boy);
                 }
-                (Pressed, winit_key) => {
-                    if let Some(key) = winit_to_joypad(winit_key) {
-                        game_boy.key_down(key);
+                (Pressed, Key::Named(named_key @ (NamedKey::F5 | NamedKey::F6 | NamedKey::F7))) if !key_event.repeat => {
+                    let layer = match named_key {
+                        NamedKey::F5 => Layers::background,
+                        NamedKey::F6 => Layers::window,
+                        _ => Layers::sprites,
+                    };
+                    let mut layer_mask = game_boy.layer_mask();
+                    match shift_pressed {
+                        true => layer_mask.highlighted.toggle(layer),
+                        false => layer_mask.hidden.toggle(layer),
                     }
+                    game_boy.set_layer_mask(layer_mask);
                 }
-                (Released, winit_key) => {
-                    if let Some(key) = winit_to_joypad(winit_key) {
-                        game_boy.key_up(key);
+                (state, _) => {
+                    let action = key_name(&key_event.logical_key).and_then(|name| input_config.keyboard.action(&name));
+                    if let Some(action) = action {
+                        actions.push((action, state == Pressed, key_event.repeat));
                     }
                 }
             }
         }
+        #[cfg(feature = "gamepad")]
+        if let Some(ref mut gamepads) = gamepads {
+            actions.extend(gamepads.poll().into_iter().map(|(action, pressed)| (action, pressed, false)));
+        }
 
-        if let Event::WindowEvent { event: WindowEvent::CloseRequested, .. } = &event {
-            elwt.exit();
-            window.request_redraw();
+        for (action, pressed, repeat) in actions {
+            match (action, pressed) {
+                (Action::Joypad(key), true) if !repeat => game_boy.key_down(key),
+                (Action::Joypad(key), false) => game_boy.key_up(key),
+                (Action::Turbo(key), true) if !repeat => game_boy.turbo_down(key),
+                (Action::Turbo(key), false) => game_boy.turbo_up(key),
+                (Action::Macro(index), true) if !repeat => game_boy.play_macro(input_config.macros[index].1.clone()),
+                (Action::Hotkey(Hotkey::FrameAdvance), true) => frame_pacer.advance_frame(),
+                (Action::Hotkey(hotkey @ (Hotkey::SpeedUp | Hotkey::SpeedDown)), true) => {
+                    frame_pacer.step_speed(hotkey == Hotkey::SpeedUp);
+                    if let Speed::Multiplier(multiplier) = frame_pacer.speed() {
+                        info!("Hastighet: {}x", multiplier);
+                    }
+                }
+                (Action::Hotkey(hotkey), true) if !repeat => match hotkey {
+                    Hotkey::Quit => {
+                        elwt.exit();
+                        window.request_redraw();
+                    }
+                    Hotkey::Pause => {
+                        frame_pacer.toggle_pause();
+                        info!("{}", if frame_pacer.paused() { "Pause" } else { "Fortsetter" });
+                    }
+                    Hotkey::FastForward => {
+                        speed_before_turbo = frame_pacer.speed();
+                        frame_pacer.set_speed(Speed::Turbo);
+                    }
+                    Hotkey::NextPalette => {
+                        palette_index = (palette_index + 1) % palettes.len();
+                        game_boy.set_palette(palettes[palette_index].clone());
+                        info!("Palett: {}", palettes[palette_index].name);
+                    }
+                    Hotkey::Screenshot => save_screenshot(&game_boy),
+                    Hotkey::ReloadCheats => load_cheats(&mut game_boy, &cheats_path),
+                    Hotkey::RecordMacro if game_boy.recording_macro() => {
+                        recorded_macro = game_boy.stop_macro_recording();
+                        match recorded_macro {
+                            Some(ref input_macro) => info!("Makro spilt inn: {}", input_macro),
+                            None => info!("Ingen taster ble trykket, så makroen er tom"),
+                        }
+                    }
+                    Hotkey::RecordMacro => {
+                        game_boy.record_macro();
+                        info!("Spiller inn makro");
+                    }
+                    Hotkey::PlayMacro => match recorded_macro {
+                        Some(ref input_macro) => game_boy.play_macro(input_macro.clone()),
+                        None => info!("Ingen makro er spilt inn"),
+                    },
+                    Hotkey::SaveState | Hotkey::LoadState | Hotkey::Rewind => warn!("{} er ikke støttet ennå", hotkey.name()),
+                    Hotkey::FrameAdvance | Hotkey::SpeedUp | Hotkey::SpeedDown => unreachable!(),
+                },
+                (Action::Hotkey(Hotkey::FastForward), false) => frame_pacer.set_speed(speed_before_turbo),
+                _ => {}
+            }
         }
 
-        let time_elapsed = start.elapsed();
-        if frame_duration > time_elapsed {
-            thread::sleep(frame_duration - time_elapsed);
+        if let Event::WindowEvent { event: WindowEvent::ModifiersChanged(modifiers), .. } = &event {
+            shift_pressed = modifiers.state().shift_key();
         }
+
+        if let Event::WindowEvent { event: WindowEvent::CloseRequested, window_id } = &event {
+            if *window_id == window.id() {
+                elwt.exit();
+                window.request_redraw();
+            } else {
+                debug_windows.retain(|debug_window| debug_window.id() != *window_id);
+            }
+        }
+
+        let time_until_next_frame = frame_pacer.time_until_next_frame(clock_start.elapsed().as_secs_f64());
+        thread::sleep(Duration::from_secs_f64(time_until_next_frame));
     });
 
+    if let Some((path, rom)) = record && let Some(movie) = game_boy.stop_recording() {
+        match movie.export(&path, &rom).map_err(str::to_string).and_then(|bytes| std::fs::write(&path, bytes).map_err(|err| err.to_string())) {
+            Ok(()) => info!("Lagret {} tastetrykk i {}", movie.events.len(), path.display()),
+            Err(err) => error!("Kunne ikke lagre filmen {}: {}", path.display(), err),
+        }
+    }
+
     res.map_err(|e| Error::UserDefined(Box::new(e)))
 }
 
-fn winit_to_joypad(key: winit::keyboard::Key<&str>) -> Option<JoypadKey> {
-    use winit::keyboard::{Key, NamedKey};
-
-    match key {
-        Key::Character("Z" | "z") => Some(JoypadKey::A),
-        Key::Character("X" | "x") => Some(JoypadKey::B),
-        Key::Named(NamedKey::ArrowUp) => Some(JoypadKey::Up),
-        Key::Named(NamedKey::ArrowDown) => Some(JoypadKey::Down),
-        Key::Named(NamedKey::ArrowLeft) => Some(JoypadKey::Left),
-        Key::Named(NamedKey::ArrowRight) => Some(JoypadKey::Right),
-        Key::Named(NamedKey::Backspace) => Some(JoypadKey::Select),
-        Key::Named(NamedKey::Enter) => Some(JoypadKey::Start),
-        _ => None,
+fn toggle_debug_window(debug_windows: &mut Vec<DebugWindow>, viewer: Viewer, elwt: &winit::event_loop::ActiveEventLoop, scale: u8, game_boy: &GameBoy) {
+    if debug_windows.iter().any(|debug_window| debug_window.viewer == viewer) {
+        debug_windows.retain(|debug_window| debug_window.viewer != viewer);
+        return
+    }
+    match DebugWindow::open(elwt, viewer, scale, game_boy) {
+        Ok(debug_window) => debug_windows.push(debug_window),
+        Err(err) => error!("Kunne ikke åpne feilsøkingsvindu: {}", err),
+    }
+}
+
+fn save_screenshot(game_boy: &GameBoy) {
+    let (width, height) = game_boy.screen_size();
+    let mut rgba = vec![0; (width * height * 4) as usize];
+    game_boy.write_frame(&mut rgba);
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis()).unwrap_or(0);
+    let path = format!("screenshot-{}.png", time);
+    let result = File::create(&path).map_err(png::EncodingError::from).and_then(|file| {
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&rgba)
+    });
+    match result {
+        Ok(()) => info!("Lagret skjermbilde i {}", path),
+        Err(err) => error!("Kunne ikke lagre skjermbilde: {}", err),
     }
 }
@@ -1,18 +1,160 @@
-import init, { main } from "./pkg/gameboy_web.js";
+import init, {
+  main, palette_names, set_palette, set_speed, toggle_pause, advance_frame,
+  add_cheat, remove_cheat, set_cheat_enabled, cheats_text, load_cheats, cheat_codes, cheat_descriptions, cheats_enabled,
+  ram_search_start, ram_search_filter, ram_search_results, ram_search_cheat,
+  archive_rom_names, archive_rom,
+} from "./pkg/gameboy_web.js";
 
 await init();
 
+const paletteSelect = document.getElementById("palette-select");
+for (const name of palette_names()) {
+  paletteSelect.add(new Option(name, name));
+}
+const paletteFromLocalStorage = localStorage.getItem('palette');
+if (paletteFromLocalStorage && palette_names().includes(paletteFromLocalStorage)) {
+  paletteSelect.value = paletteFromLocalStorage;
+  set_palette(paletteFromLocalStorage);
+}
+paletteSelect.addEventListener("change", () => {
+  localStorage.setItem('palette', paletteSelect.value);
+  set_palette(paletteSelect.value);
+});
+
+const speedSelect = document.getElementById("speed-select");
+speedSelect.addEventListener("change", () => {
+  set_speed(Number(speedSelect.value));
+});
+
+const pauseButton = document.getElementById("pause-button");
+pauseButton.addEventListener("click", () => {
+  pauseButton.textContent = toggle_pause() ? "Fortsett" : "Pause";
+});
+
+document.getElementById("advance-frame-button").addEventListener("click", () => {
+  advance_frame();
+  pauseButton.textContent = "Fortsett";
+});
+
 // Videresend tastatur-events til canvas slik at winit
 // fanger dem opp uansett hvilket element som har fokus.
 for (const type of ["keydown", "keyup"]) {
   document.addEventListener(type, (e) => {
     const canvas = document.querySelector("#screen canvas");
-    if (canvas && e.target !== canvas) {
+    // Tekst som skrives inn i menyen, f.eks. juksekoder, skal ikke gå til spillet
+    if (canvas && e.target !== canvas && !(e.target instanceof HTMLInputElement)) {
       canvas.dispatchEvent(new KeyboardEvent(type, e));
     }
   });
 }
 
+// Juksekodene lagres per spill i samme tekstformat som .cht-filene i vinduversjonen
+const cheatList = document.getElementById("cheat-list");
+const cheatError = document.getElementById("cheat-error");
+
+function saveCheats() {
+  const romTitle = localStorage.getItem('rom-title');
+  if (romTitle) {
+    localStorage.setItem(`cheats-${romTitle}`, cheats_text());
+  }
+  renderCheats();
+}
+
+function renderCheats() {
+  const descriptions = cheat_descriptions();
+  const enabled = cheats_enabled();
+  cheatList.replaceChildren(...cheat_codes().map((code, index) => {
+    const item = document.createElement("li");
+    const label = document.createElement("label");
+    const checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.checked = enabled[index] === 1;
+    checkbox.addEventListener("change", () => {
+      set_cheat_enabled(index, checkbox.checked);
+      saveCheats();
+    });
+    label.append(checkbox, ` ${code} ${descriptions[index]}`);
+    const removeButton = document.createElement("button");
+    removeButton.textContent = "Fjern";
+    removeButton.addEventListener("click", () => {
+      remove_cheat(index);
+      saveCheats();
+    });
+    item.append(label, removeButton);
+    return item;
+  }));
+}
+
+document.getElementById("cheat-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  const code = document.getElementById("cheat-code");
+  const description = document.getElementById("cheat-description");
+  try {
+    add_cheat(code.value, description.value);
+    cheatError.textContent = "";
+    code.value = "";
+    description.value = "";
+    saveCheats();
+  } catch (err) {
+    cheatError.textContent = err.message;
+  }
+});
+
+// RAM-søk for å finne adresser til egne juksekoder. Verdiene oppdateres mens panelet er åpent
+const ramSearchPanel = document.getElementById("ram-search-panel");
+const ramSearchCount = document.getElementById("ram-search-count");
+const ramSearchRows = document.querySelector("#ram-search-results tbody");
+let ramSearchTimer;
+
+function renderRamSearch() {
+  const search = JSON.parse(ram_search_results());
+  ramSearchCount.textContent = search.type ? `${search.count} treff` : "Start et søk";
+  ramSearchRows.replaceChildren(...search.results.map((result) => {
+    const row = document.createElement("tr");
+    const address = `0x${result.address.toString(16).padStart(4, "0")}`;
+    for (const text of [address, result.value, result.previous]) {
+      const cell = document.createElement("td");
+      cell.textContent = text;
+      row.append(cell);
+    }
+    const lockButton = document.createElement("button");
+    lockButton.textContent = "Lås";
+    lockButton.addEventListener("click", () => {
+      ram_search_cheat(result.address, result.value);
+      saveCheats();
+    });
+    const cell = document.createElement("td");
+    cell.append(lockButton);
+    row.append(cell);
+    return row;
+  }));
+}
+
+ramSearchPanel.addEventListener("toggle", (e) => {
+  clearInterval(ramSearchTimer);
+  if (e.newState === "open") {
+    renderRamSearch();
+    ramSearchTimer = setInterval(renderRamSearch, 250);
+  }
+});
+
+document.getElementById("ram-search-start").addEventListener("click", () => {
+  ram_search_start(document.getElementById("ram-search-type").value);
+});
+
+for (const button of ramSearchPanel.querySelectorAll("[data-filter]")) {
+  button.addEventListener("click", () => ram_search_filter(button.dataset.filter));
+}
+
+document.getElementById("ram-search-value-form").addEventListener("submit", (e) => {
+  e.preventDefault();
+  try {
+    ram_search_filter(document.getElementById("ram-search-value").value.trim());
+  } catch (err) {
+    ramSearchCount.textContent = err.message;
+  }
+});
+
 const romDropZone = document.getElementById("rom-drop-zone");
 const fileInput = document.getElementById("game-pak-input");
 
@@ -53,9 +195,7 @@ romDropZone.addEventListener("drop", async (e) => {
     return;
   }
 
-  const game_title = rom_file.name.split(".")[0];
-  const bytes = new Uint8Array(await rom_file.arrayBuffer());
-  await loadRom(game_title, bytes);
+  await loadRomFile(rom_file);
 });
 
 fileInput.addEventListener("change", async (e) => {
@@ -63,15 +203,45 @@ fileInput.addEventListener("change", async (e) => {
   if (!rom_file) {
     return;
   }
-  const game_title = rom_file.name.split(".")[0];
-  const bytes = new Uint8Array(await rom_file.arrayBuffer());
-  await loadRom(game_title, bytes);
+  await loadRomFile(rom_file);
 });
 
+// Pakker ut arkiver først. Spør hvilken ROM som skal brukes når arkivet har flere
+async function loadRomFile(romFile) {
+  let bytes = new Uint8Array(await romFile.arrayBuffer());
+  let name = romFile.name;
+  try {
+    const names = archive_rom_names(bytes);
+    if (names.length > 0) {
+      name = names[0];
+      if (names.length > 1) {
+        const list = names.map((name, i) => `${i + 1}: ${name}`).join("\n");
+        const choice = Number(prompt(`Arkivet har flere ROM-er. Velg en:\n${list}`, "1"));
+        if (!Number.isInteger(choice) || choice < 1 || choice > names.length) {
+          return;
+        }
+        name = names[choice - 1];
+      }
+      bytes = archive_rom(bytes, name);
+    }
+  } catch (err) {
+    alert(err.message);
+    return;
+  }
+  const game_title = name.split("/").pop().split(".")[0];
+  await loadRom(game_title, bytes);
+}
+
 async function loadRom(romTitle, romData) {
   romDropZone.style.display = "none";
   localStorage.setItem('rom-title', romTitle);
   localStorage.setItem('rom-data', JSON.stringify(Array.from(romData)));
+  try {
+    load_cheats(localStorage.getItem(`cheats-${romTitle}`) ?? "");
+  } catch (err) {
+    console.error("Kunne ikke lese lagrede juksekoder:", err);
+  }
+  renderCheats();
   main(romTitle, romData);
 }
 
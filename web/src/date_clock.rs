@@ -0,0 +1,10 @@
+use gameboy_core::rtc::Clock;
+
+// SystemTime finnes ikke i nettleseren, så sanntidsklokken hentes fra JavaScript sin Date
+pub struct DateClock;
+
+impl Clock for DateClock {
+    fn now(&self) -> u64 {
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+}
@@ -1,23 +1,203 @@
+mod date_clock;
 mod local_storage_battery_save;
 
 use log::{error, info};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use pixels::{PixelsBuilder, SurfaceTexture};
 use wasm_bindgen::prelude::*;
 use winit::dpi::LogicalSize;
 use winit::event_loop::EventLoop;
-use winit::keyboard::{Key, NamedKey};
 #[cfg(target_arch = "wasm32")]
 use winit::platform::web::WindowExtWebSys;
 use winit::window::Window;
 
-use gameboy_core::{SCREEN_HEIGHT, SCREEN_WIDTH};
-use gameboy_core::battery_save::BatterySave;
+use gameboy_core::archive::{self, ArchiveFormat};
+use gameboy_core::cheats::{Cheat, Cheats};
 use gameboy_core::frame_buffer::FrameBuffer;
 use gameboy_core::game_boy::GameBoy;
-use gameboy_core::joypad::JoypadKey;
+use gameboy_core::pacing::{FramePacer, Speed};
+use gameboy_core::ram_search::{RamSearch, SearchFilter, SearchResult, ValueType};
+use gameboy_core::palette::Palette;
+use gameboy_input::Action;
+use gameboy_input::config::InputConfig;
+use gameboy_input::keyboard::key_name;
+use crate::date_clock::DateClock;
 use crate::local_storage_battery_save::LocalStorageBatterySave;
 
+thread_local! {
+    static PALETTE: RefCell<Palette> = RefCell::new(Palette::gray());
+    static FRAME_PACER: RefCell<FramePacer> = RefCell::new(FramePacer::new());
+    // Juksekodene fra menyen. Kopieres til GameBoy-en før neste frame når de er endret
+    static CHEATS: RefCell<Cheats> = RefCell::new(Cheats::new());
+    static CHEATS_CHANGED: Cell<bool> = const { Cell::new(false) };
+    // RAM-søket trenger GameBoy-en, så nye søk og filtre kjøres før neste frame
+    static RAM_SEARCH: RefCell<Option<RamSearch>> = const { RefCell::new(None) };
+    static RAM_SEARCH_COMMANDS: RefCell<Vec<RamSearchCommand>> = const { RefCell::new(vec![]) };
+    static RAM_SEARCH_RESULTS: RefCell<String> = RefCell::new(ram_search_json(None, &[]));
+}
+
+enum RamSearchCommand {
+    Start(ValueType),
+    Filter(SearchFilter),
+}
+
+// Antall treff som vises i menyen
+const RAM_SEARCH_LIMIT: usize = 50;
+
+#[wasm_bindgen]
+pub fn palette_names() -> Vec<String> {
+    Palette::presets().into_iter().map(|palette| palette.name).collect()
+}
+
+#[wasm_bindgen]
+pub fn set_palette(name: String) {
+    match Palette::by_name(&name) {
+        Some(palette) => PALETTE.set(palette),
+        None => error!("Fant ingen palett med navnet {}", name),
+    }
+}
+
+// 0 betyr turbo
+#[wasm_bindgen]
+pub fn set_speed(multiplier: f64) {
+    let speed = if multiplier > 0.0 { Speed::Multiplier(multiplier) } else { Speed::Turbo };
+    FRAME_PACER.with_borrow_mut(|frame_pacer| frame_pacer.set_speed(speed));
+}
+
+#[wasm_bindgen]
+pub fn toggle_pause() -> bool {
+    FRAME_PACER.with_borrow_mut(|frame_pacer| {
+        frame_pacer.toggle_pause();
+        frame_pacer.paused()
+    })
+}
+
+#[wasm_bindgen]
+pub fn advance_frame() {
+    FRAME_PACER.with_borrow_mut(|frame_pacer| frame_pacer.advance_frame());
+}
+
+fn update_cheats(update: impl FnOnce(&mut Cheats)) {
+    CHEATS.with_borrow_mut(update);
+    CHEATS_CHANGED.set(true);
+}
+
+#[wasm_bindgen]
+pub fn add_cheat(code: String, description: String) -> Result<(), JsError> {
+    let cheat = Cheat::parse(&code, &description).map_err(JsError::new)?;
+    update_cheats(|cheats| cheats.add(cheat));
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn remove_cheat(index: usize) {
+    update_cheats(|cheats| { cheats.remove(index); });
+}
+
+#[wasm_bindgen]
+pub fn set_cheat_enabled(index: usize, enabled: bool) {
+    update_cheats(|cheats| cheats.set_enabled(index, enabled));
+}
+
+// Samme tekstformat som juksekodefilene i vinduversjonen, så listen kan lagres i localStorage
+#[wasm_bindgen]
+pub fn cheats_text() -> String {
+    CHEATS.with_borrow(Cheats::to_text)
+}
+
+#[wasm_bindgen]
+pub fn load_cheats(text: String) -> Result<(), JsError> {
+    let loaded = Cheats::parse(&text).map_err(|err| JsError::new(&err))?;
+    update_cheats(|cheats| *cheats = loaded);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn cheat_codes() -> Vec<String> {
+    CHEATS.with_borrow(|cheats| cheats.list().iter().map(|cheat| cheat.code.clone()).collect())
+}
+
+#[wasm_bindgen]
+pub fn cheat_descriptions() -> Vec<String> {
+    CHEATS.with_borrow(|cheats| cheats.list().iter().map(|cheat| cheat.description.clone()).collect())
+}
+
+#[wasm_bindgen]
+pub fn cheats_enabled() -> Vec<u8> {
+    CHEATS.with_borrow(|cheats| cheats.list().iter().map(|cheat| cheat.enabled as u8).collect())
+}
+
+#[wasm_bindgen]
+pub fn ram_search_start(value_type: String) -> Result<(), JsError> {
+    let value_type = ValueType::from_name(&value_type).ok_or(JsError::new("Ukjent type. Bruk u8, u16, bcd8 eller bcd16"))?;
+    RAM_SEARCH_COMMANDS.with_borrow_mut(|commands| commands.push(RamSearchCommand::Start(value_type)));
+    Ok(())
+}
+
+// `eq`, `ne`, `inc`, `dec` eller en verdi
+#[wasm_bindgen]
+pub fn ram_search_filter(filter: String) -> Result<(), JsError> {
+    let filter = SearchFilter::parse(&filter).ok_or(JsError::new("Ukjent filter"))?;
+    RAM_SEARCH_COMMANDS.with_borrow_mut(|commands| commands.push(RamSearchCommand::Filter(filter)));
+    Ok(())
+}
+
+// JSON med typen, antall treff og de første treffene med verdien nå og ved forrige filtrering
+#[wasm_bindgen]
+pub fn ram_search_results() -> String {
+    RAM_SEARCH_RESULTS.with_borrow(String::clone)
+}
+
+// Legger til GameShark-koder som holder verdien fast, med typen fra søket
+#[wasm_bindgen]
+pub fn ram_search_cheat(address: u16, value: u32) -> Result<(), JsError> {
+    let value_type = RAM_SEARCH.with_borrow(|search| search.as_ref().map_or(ValueType::U8, RamSearch::value_type));
+    let new_cheats = value_type.cheats(address, value).map_err(JsError::new)?;
+    update_cheats(|cheats| new_cheats.into_iter().for_each(|cheat| cheats.add(cheat)));
+    Ok(())
+}
+
+fn ram_search_json(search: Option<&RamSearch>, results: &[SearchResult]) -> String {
+    let results: Vec<String> = results.iter()
+        .map(|result| format!(r#"{{"address":{},"value":{},"previous":{}}}"#, result.address, result.value, result.previous))
+        .collect();
+    match search {
+        Some(search) => format!(r#"{{"type":"{}","count":{},"results":[{}]}}"#, search.value_type().name(), search.len(), results.join(",")),
+        None => r#"{"type":null,"count":0,"results":[]}"#.to_string(),
+    }
+}
+
+fn update_ram_search(game_boy: &GameBoy) {
+    for command in RAM_SEARCH_COMMANDS.take() {
+        match command {
+            RamSearchCommand::Start(value_type) => RAM_SEARCH.set(Some(RamSearch::new(game_boy, value_type))),
+            RamSearchCommand::Filter(filter) => RAM_SEARCH.with_borrow_mut(|search| {
+                if let Some(search) = search {
+                    search.filter(game_boy, filter)
+                }
+            }),
+        }
+    }
+    RAM_SEARCH.with_borrow(|search| {
+        if let Some(search) = search {
+            RAM_SEARCH_RESULTS.set(ram_search_json(Some(search), &search.results(game_boy, RAM_SEARCH_LIMIT)));
+        }
+    });
+}
+
+// ROM-ene i et zip-, 7z- eller gzip-arkiv. Tom for vanlige ROM-er
+#[wasm_bindgen]
+pub fn archive_rom_names(data: &[u8]) -> Result<Vec<String>, JsError> {
+    if ArchiveFormat::detect(data).is_none() { return Ok(vec![]) }
+    archive::rom_names(data).map_err(JsError::new)
+}
+
+#[wasm_bindgen]
+pub fn archive_rom(data: &[u8], name: String) -> Result<Vec<u8>, JsError> {
+    archive::extract(data, &name).map_err(JsError::new)
+}
+
 #[wasm_bindgen]
 pub fn main(game_title: String, rom_data: Vec<u8>) {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -26,18 +206,22 @@ pub fn main(game_title: String, rom_data: Vec<u8>) {
 }
 
 async fn run(game_title: String, rom_data: Vec<u8>) {
-    let local_storage_battery_save = LocalStorageBatterySave::new(&game_title)
-        .map(|battery_save| Box::new(battery_save) as Box<dyn BatterySave>);
+    let mut builder = GameBoy::builder(rom_data)
+        .clock(Box::new(DateClock));
+    if let Some(battery_save) = LocalStorageBatterySave::new(&game_title) {
+        builder = builder.battery_save(Box::new(battery_save));
+    }
 
-    let mut game_boy = match GameBoy::new(rom_data, local_storage_battery_save) {
+    let mut game_boy = match builder.build() {
         Ok(game_boy) => game_boy,
         Err(error_str) => panic!("{}", error_str),
     };
 
     let event_loop = EventLoop::new().unwrap();
     let scale = 3;
+    let (screen_width, screen_height) = game_boy.screen_size();
     let window = {
-        let size = LogicalSize::new(SCREEN_WIDTH as f64 * scale as f64, SCREEN_HEIGHT as f64 * scale as f64);
+        let size = LogicalSize::new(screen_width as f64 * scale as f64, screen_height as f64 * scale as f64);
         event_loop.create_window(
             Window::default_attributes()
                 .with_title("Game Boy Web")
@@ -60,10 +244,10 @@ async fn run(game_title: String, rom_data: Vec<u8>) {
         .expect("Kunne ikke legge canvas til DOM");
 
     let mut pixels = {
-        let surface_width = SCREEN_WIDTH * scale;
-        let surface_height = SCREEN_HEIGHT * scale;
+        let surface_width = screen_width * scale;
+        let surface_height = screen_height * scale;
         let surface_texture = SurfaceTexture::new(surface_width, surface_height, window.clone());
-        let builder = PixelsBuilder::new(SCREEN_WIDTH, SCREEN_HEIGHT, surface_texture)
+        let builder = PixelsBuilder::new(screen_width, screen_height, surface_texture)
             .texture_format(pixels::wgpu::TextureFormat::Rgba8Unorm)
             .surface_texture_format(pixels::wgpu::TextureFormat::Rgba8Unorm);
 
@@ -72,9 +256,10 @@ async fn run(game_title: String, rom_data: Vec<u8>) {
 
     info!("Pixels opprettet");
 
-    let cpu_cycles_per_frame = (4194204f64 / 1000.0 * 16.0).round() as u32;
-    let mut cpu_cycles: u32 = 0;
 
+    // Nettleseren kan ikke lese input.toml, så den bruker alltid standardoppsettet
+    let input_config = InputConfig::default();
+    game_boy.set_turbo_period(input_config.turbo_period());
     let frames_between_saves = 120;
     let mut frames_since_save = 0;
 
@@ -84,13 +269,15 @@ async fn run(game_title: String, rom_data: Vec<u8>) {
 
         match event {
             Event::AboutToWait => {
-                while cpu_cycles < cpu_cycles_per_frame {
-                    cpu_cycles += game_boy.emulate();
+                if CHEATS_CHANGED.replace(false) {
+                    *game_boy.cheats_mut() = CHEATS.with_borrow(Cheats::clone);
                 }
-                cpu_cycles -= cpu_cycles_per_frame;
+                let frames = FRAME_PACER.with_borrow_mut(|frame_pacer| frame_pacer.frames_due(js_sys::Date::now() / 1000.0));
+                game_boy.run_frames(frames);
+                update_ram_search(&game_boy);
 
                 if let Some(data) = game_boy.updated_frame_buffer() {
-                    data.write_to_rbga_buffer(pixels.frame_mut());
+                    PALETTE.with_borrow(|palette| data.write_to_rbga_buffer(palette, pixels.frame_mut()));
                     if let Err(err) = pixels.render() {
                         error!("Feil under tegning til skjerm!");
                         elwt.exit();
@@ -106,17 +293,14 @@ async fn run(game_title: String, rom_data: Vec<u8>) {
                 window.request_redraw();
             }
             Event::WindowEvent { event: WindowEvent::KeyboardInput { event: key_event, .. }, .. } => {
-                match (key_event.state, key_event.logical_key.as_ref()) {
-                    (Pressed, winit_key) => {
-                        if let Some(key) = winit_to_joypad(winit_key) {
-                            game_boy.key_down(key);
-                        }
-                    }
-                    (Released, winit_key) => {
-                        if let Some(key) = winit_to_joypad(winit_key) {
-                            game_boy.key_up(key);
-                        }
-                    }
+                // Hurtigtastene har egne knapper i menyen, så her brukes bare tastene til spillet og turbo
+                let action = key_name(&key_event.logical_key).and_then(|name| input_config.keyboard.action(&name));
+                match (key_event.state, action) {
+                    (Pressed, Some(Action::Joypad(key))) => game_boy.key_down(key),
+                    (Released, Some(Action::Joypad(key))) => game_boy.key_up(key),
+                    (Pressed, Some(Action::Turbo(key))) => game_boy.turbo_down(key),
+                    (Released, Some(Action::Turbo(key))) => game_boy.turbo_up(key),
+                    _ => {}
                 }
                 window.request_redraw();
             }
@@ -133,17 +317,3 @@ async fn run(game_title: String, rom_data: Vec<u8>) {
     });
     res.unwrap();
 }
-
-fn winit_to_joypad(key: Key<&str>) -> Option<JoypadKey> {
-    match key {
-        Key::Character("Z" | "z") => Some(JoypadKey::A),
-        Key::Character("X" | "x") => Some(JoypadKey::B),
-        Key::Named(NamedKey::ArrowUp) => Some(JoypadKey::Up),
-        Key::Named(NamedKey::ArrowDown) => Some(JoypadKey::Down),
-        Key::Named(NamedKey::ArrowLeft) => Some(JoypadKey::Left),
-        Key::Named(NamedKey::ArrowRight) => Some(JoypadKey::Right),
-        Key::Named(NamedKey::Backspace) => Some(JoypadKey::Select),
-        Key::Named(NamedKey::Enter) => Some(JoypadKey::Start),
-        _ => None,
-    }
-}
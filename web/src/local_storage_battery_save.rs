@@ -18,15 +18,18 @@ impl LocalStorageBatterySave {
 }
 
 impl BatterySave for LocalStorageBatterySave {
-    fn load(&self, ram: &mut [u8]) {
+    fn load(&self, ram: &mut [u8]) -> usize {
         match self.local_storage.get_item(&self.local_storage_key) {
             Ok(Some(value)) => {
                 let decoded = BASE64_STANDARD.decode(value).unwrap();
-                ram.copy_from_slice(&decoded);
+                let length = decoded.len().min(ram.len());
+                ram[..length].copy_from_slice(&decoded[..length]);
+                length
             }
-            Ok(None) => {}
+            Ok(None) => 0,
             Err(error) => {
                 error!("Klarte ikke å lese lagret spill fra LocalStorage: {:?}", error);
+                0
             }
         }
     }